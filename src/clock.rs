@@ -0,0 +1,51 @@
+use std::{ sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+
+
+
+/// A source of the current time.
+/// Allows replacing the time used by the system for delayed profile changes in tests.
+pub trait Clock:Send + Sync + 'static {
+
+	/// Get the current time.
+	fn now(&self) -> Instant;
+}
+
+
+
+/// A clock using the real time.
+#[derive(Clone, Copy, Default)]
+pub struct SystemClock;
+impl Clock for SystemClock {
+	fn now(&self) -> Instant {
+		Instant::now()
+	}
+}
+
+
+
+/// A clock that only moves when it is advanced.
+/// Clones share the same time, so a test can keep a clone to advance the clock after handing it to the system.
+#[derive(Clone)]
+pub struct FakeClock(Arc<Mutex<Instant>>);
+impl FakeClock {
+
+	/// Create a new fake clock starting at the current time.
+	pub fn new() -> FakeClock {
+		FakeClock(Arc::new(Mutex::new(Instant::now())))
+	}
+
+	/// Move the clock forward by the given duration.
+	pub fn advance(&self, duration:Duration) {
+		*self.0.lock().unwrap() += duration;
+	}
+}
+impl Default for FakeClock {
+	fn default() -> Self {
+		FakeClock::new()
+	}
+}
+impl Clock for FakeClock {
+	fn now(&self) -> Instant {
+		*self.0.lock().unwrap()
+	}
+}
\ No newline at end of file
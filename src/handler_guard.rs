@@ -0,0 +1,185 @@
+use crate::{ ActiveWindowInfo, ProfileError, ProfilePhase, WindowRelativeProfile, WindowRelativeProfileStatus, system };
+use std::{ any::Any, collections::HashMap, error::Error, fmt::{ self, Display, Formatter }, panic::{ self, AssertUnwindSafe }, sync::{ Arc, Mutex } };
+
+
+
+pub(crate) type ErrorHandler = Arc<dyn Fn(ProfileError) + Send + Sync + 'static>;
+
+
+
+/// The error given to the error handler when a handler of a profile panicked.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct HandlerPanic {
+	pub message:String
+}
+impl HandlerPanic {
+
+	/// Create a new handler panic error from the payload of a caught panic.
+	fn from_payload(payload:Box<dyn Any + Send>) -> HandlerPanic {
+		let message:String = match payload.downcast_ref::<&str>() {
+			Some(message) => message.to_string(),
+			None => payload.downcast_ref::<String>().cloned().unwrap_or_else(|| "unknown panic".to_string())
+		};
+		HandlerPanic { message }
+	}
+}
+impl Display for HandlerPanic {
+	fn fmt(&self, f:&mut Formatter<'_>) -> fmt::Result {
+		write!(f, "handler panicked: {}", self.message)
+	}
+}
+impl Error for HandlerPanic {}
+
+
+
+/// Identifies the profile failures are counted for.
+/// Profiles are identified by the order they were added in, the default profile by None.
+/// Per-window instances are counted separately for each window, other profiles and templates without window.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub(crate) struct FaultKey {
+	pub(crate) profile_index:Option<usize>,
+	pub(crate) window_id:Option<u64>
+}
+impl FaultKey {
+
+	/// Create a new key for the profile with the given index and the instance of the given window.
+	pub(crate) fn new(profile_index:Option<usize>, window_id:Option<u64>) -> FaultKey {
+		FaultKey { profile_index, window_id }
+	}
+}
+
+
+
+/// Runs the handlers of profiles, isolating panics and faulting profiles that keep failing.
+/// Clones share the failure counts.
+#[derive(Clone)]
+pub(crate) struct HandlerGuard {
+	pub(crate) error_handler:ErrorHandler,
+	pub(crate) fault_threshold:Option<usize>,
+	consecutive_failures:Arc<Mutex<HashMap<FaultKey, usize>>>
+}
+impl HandlerGuard {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new guard giving errors to the given error handler, never faulting profiles.
+	pub(crate) fn new(error_handler:ErrorHandler) -> HandlerGuard {
+		HandlerGuard {
+			error_handler,
+			fault_threshold: None,
+			consecutive_failures: Arc::new(Mutex::new(HashMap::new()))
+		}
+	}
+
+
+
+	/* PROPERTY GETTER METHODS */
+
+	/// Get the amount of dispatches in a row in which a handler of the profile with the given key failed.
+	pub(crate) fn consecutive_failures(&self, key:FaultKey) -> usize {
+		self.consecutive_failures.lock().unwrap().get(&key).copied().unwrap_or_default()
+	}
+
+	/// Whether or not the profile with the given key failed too often to run its handlers.
+	pub(crate) fn is_faulted(&self, key:FaultKey) -> bool {
+		self.fault_threshold.is_some_and(|fault_threshold| self.consecutive_failures(key) >= fault_threshold)
+	}
+
+
+
+	/* USAGE METHODS */
+
+	/// Forget the failures of the profile with the given index, including those of its per-window instances.
+	pub(crate) fn reset(&self, profile_index:Option<usize>) {
+		self.consecutive_failures.lock().unwrap().retain(|key, _| key.profile_index != profile_index);
+	}
+
+	/// Forget the failures of the profile with the given key.
+	/// Used when a per-window instance is discarded.
+	pub(crate) fn forget(&self, key:FaultKey) {
+		self.consecutive_failures.lock().unwrap().remove(&key);
+	}
+
+	/// Run the handlers of a single dispatch on a profile, like an activation or a triggered event, for the given window.
+	/// A dispatch in which any handler failed counts as a failure of the profile, any other dispatch resets its failures.
+	/// Once the profile is faulted, its status is set to `Faulted` and its handlers are no longer run. Returns None in that case.
+	pub(crate) fn dispatch<T, Handlers:FnOnce(&mut HandlerDispatch<'_>) -> T>(&self, key:FaultKey, profile:&mut dyn WindowRelativeProfile, window:&ActiveWindowInfo, handlers:Handlers) -> Option<T> {
+		if self.is_faulted(key) {
+			self.mark_faulted(key, profile, window);
+			return None;
+		}
+		let mut dispatch:HandlerDispatch<'_> = HandlerDispatch { guard: self, profile, window, failed: false };
+		let result:T = handlers(&mut dispatch);
+		let HandlerDispatch { profile, failed, .. } = dispatch;
+
+		// Keep track of the failures of the profile.
+		let mut consecutive_failures = self.consecutive_failures.lock().unwrap();
+		if failed {
+			*consecutive_failures.entry(key).or_default() += 1;
+		} else {
+			consecutive_failures.remove(&key);
+		}
+		drop(consecutive_failures);
+		if self.is_faulted(key) {
+			self.mark_faulted(key, profile, window);
+		}
+		Some(result)
+	}
+
+	/// Run a call on the profile with the given key and name that is not a handler, like matching a window or an action given to a remote.
+	/// A panic is given to the error handler and counts as a failure of the profile, returns None in that case. Successful calls do not reset the failures.
+	/// The status of a profile faulted by a call is set to `Faulted` on its next dispatch.
+	pub(crate) fn check<T, Call:FnOnce() -> T>(&self, key:FaultKey, profile_name:&str, phase:ProfilePhase, window:&ActiveWindowInfo, call:Call) -> Option<T> {
+		match panic::catch_unwind(AssertUnwindSafe(call)) {
+			Ok(value) => Some(value),
+			Err(payload) => {
+				*self.consecutive_failures.lock().unwrap().entry(key).or_default() += 1;
+				(self.error_handler)(ProfileError::new(profile_name, phase, None, window, Box::new(HandlerPanic::from_payload(payload))));
+				None
+			}
+		}
+	}
+
+	/// Set the status of a faulted profile and stop its tasks.
+	pub(crate) fn mark_faulted(&self, key:FaultKey, profile:&mut dyn WindowRelativeProfile, window:&ActiveWindowInfo) {
+		if profile.status() != &WindowRelativeProfileStatus::Faulted {
+			*profile.status_mut() = WindowRelativeProfileStatus::Faulted;
+			let profile_name:String = profile.name().to_string();
+			self.check(key, &profile_name, ProfilePhase::Close, window, || system::apply_status_to_tasks(profile));
+		}
+	}
+}
+
+
+
+/// The handlers run on a profile in a single dispatch.
+pub(crate) struct HandlerDispatch<'a> {
+	guard:&'a HandlerGuard,
+	profile:&'a mut dyn WindowRelativeProfile,
+	window:&'a ActiveWindowInfo,
+	failed:bool
+}
+impl HandlerDispatch<'_> {
+
+	/// Get the profile the handlers are run on.
+	pub(crate) fn profile(&mut self) -> &mut dyn WindowRelativeProfile {
+		self.profile
+	}
+
+	/// Run a handler of the profile, for the given event if the handler executes one.
+	/// Errors and panics of the handler are given to the error handler, returns None in that case.
+	pub(crate) fn run<T, Handler:FnOnce(&mut dyn WindowRelativeProfile) -> Result<T, Box<dyn Error>>>(&mut self, phase:ProfilePhase, event_name:Option<&str>, handler:Handler) -> Option<T> {
+		let result:Result<T, Box<dyn Error>> = match panic::catch_unwind(AssertUnwindSafe(|| handler(&mut *self.profile))) {
+			Ok(result) => result,
+			Err(payload) => Err(Box::new(HandlerPanic::from_payload(payload)))
+		};
+		match result {
+			Ok(value) => Some(value),
+			Err(error) => {
+				self.failed = true;
+				(self.guard.error_handler)(ProfileError::new(self.profile.name(), phase, event_name, self.window, error));
+				None
+			}
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,232 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, HandlerPanic, TaskSystem, TaskSystemPolicy, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials };
+	use std::{ error::Error, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering } } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+
+	struct FaultyProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		panic_on_activate:bool,
+		panic_on_match:bool,
+		panic_on_task_policy:bool,
+		log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(FaultyProfile);
+	impl WindowRelativeProfile for FaultyProfile {
+		fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+			if self.panic_on_match {
+				panic!("matching exploded");
+			}
+			active_window.process_name == self.process_name
+		}
+		fn task_policy(&self) -> TaskSystemPolicy {
+			if self.panic_on_task_policy {
+				panic!("task policy exploded");
+			}
+			TaskSystemPolicy::RunWhileActive
+		}
+		fn on_activate(&mut self, _window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+			if self.panic_on_activate {
+				panic!("activation exploded");
+			}
+			Ok(())
+		}
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			match event_name {
+				"fail" => Err("event failed".into()),
+				"panic" => panic!("event exploded"),
+				_ => {
+					self.log.lock().unwrap().push(format!("{}:{}", self.name, event_name));
+					Ok(())
+				}
+			}
+		}
+	}
+	impl FaultyProfile {
+		fn new(name:&'static str, process_name:&'static str, log:&Arc<Mutex<Vec<String>>>) -> FaultyProfile {
+			FaultyProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default(),
+				panic_on_activate: false,
+				panic_on_match: false,
+				panic_on_task_policy: false,
+				log: log.clone()
+			}
+		}
+	}
+
+
+
+	fn faulty_system(log:&Arc<Mutex<Vec<String>>>, errors:&Arc<Mutex<Vec<String>>>) -> WindowRelativeSystem {
+		let errors:Arc<Mutex<Vec<String>>> = errors.clone();
+		WindowRelativeSystem::new(FaultyProfile::new("default", "", log))
+			.with_profile(FaultyProfile::new("editor", "editor.exe", log))
+			.with_error_handler(move |profile_name, error| {
+				let message:String = match error.downcast_ref::<HandlerPanic>() {
+					Some(panic) => format!("{} panicked: {}", profile_name, panic.message),
+					None => format!("{} failed: {}", profile_name, error)
+				};
+				errors.lock().unwrap().push(message);
+			})
+	}
+	fn profile_status_is(system:&mut WindowRelativeSystem, name:&str, status:WindowRelativeProfileStatus) -> bool {
+		system.execute_on_profile_with_name(name, |profile| profile.status() == &status).unwrap()
+	}
+
+
+
+	#[test]
+	fn test_handler_panics_are_routed_to_error_handler() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut panicking_profile:FaultyProfile = FaultyProfile::new("browser", "browser.exe", &log);
+		panicking_profile.panic_on_activate = true;
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_profile(panicking_profile);
+		system.set_active_window(&ActiveWindowInfo::new(1, "browser.exe", ""));
+		system.trigger_event("panic");
+		assert_eq!(*errors.lock().unwrap(), vec!["browser panicked: activation exploded", "browser panicked: event exploded"]);
+
+		// The remaining handlers and other profiles keep working.
+		system.set_active_window(&ActiveWindowInfo::new(2, "editor.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec!["browser:open", "browser:activate", "browser:deactivate", "editor:open", "editor:activate"]);
+		assert!(profile_status_is(&mut system, "browser", WindowRelativeProfileStatus::Deactivated));
+	}
+
+	#[test]
+	fn test_profile_faults_after_consecutive_failures() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_fault_threshold(Some(2));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+
+		// A successful dispatch resets the failures.
+		system.trigger_event("fail");
+		system.trigger_event("ping");
+		system.trigger_event("fail");
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Active));
+		system.trigger_event("panic");
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Faulted));
+		assert_eq!(errors.lock().unwrap().len(), 3);
+
+		// Faulted profiles are skipped until they are reset.
+		log.lock().unwrap().clear();
+		system.trigger_event("ping");
+		system.set_active_window(&ActiveWindowInfo::new(2, "other.exe", ""));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec!["default:open", "default:activate", "default:window_changed"]);
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Faulted));
+		system.reset_faulted_profile("editor");
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Uninitialized));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec!["default:open", "default:activate", "default:window_changed", "default:deactivate", "editor:open", "editor:activate"]);
+	}
+
+	#[test]
+	fn test_profiles_never_fault_without_threshold() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors);
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		for _ in 0..10 {
+			system.trigger_event("fail");
+		}
+		assert_eq!(errors.lock().unwrap().len(), 10);
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Active));
+	}
+
+	#[test]
+	fn test_profiles_panicking_while_matching_are_skipped() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut panicking_profile:FaultyProfile = FaultyProfile::new("browser", "browser.exe", &log);
+		panicking_profile.panic_on_match = true;
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_profile(panicking_profile).with_fault_threshold(Some(2));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "editor");
+		system.set_active_window(&ActiveWindowInfo::new(2, "browser.exe", ""));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "default");
+		assert_eq!(*errors.lock().unwrap(), vec!["browser panicked: matching exploded", "browser panicked: matching exploded"]);
+
+		// Once faulted, the profile is no longer matched.
+		assert!(profile_status_is(&mut system, "browser", WindowRelativeProfileStatus::Faulted));
+		system.set_active_window(&ActiveWindowInfo::new(3, "browser.exe", ""));
+		assert_eq!(errors.lock().unwrap().len(), 2);
+		assert!(profile_status_is(&mut system, "editor", WindowRelativeProfileStatus::Deactivated));
+	}
+
+	#[test]
+	fn test_remote_action_panics_are_routed_to_error_handler() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_startup_activation(false);
+		let remote = system.create_remote();
+		remote.handle_window_change(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		remote.execute_on_current_profile(|_| panic!("action exploded"));
+		remote.trigger_event("ping");
+		system.run_until_idle();
+		assert_eq!(*errors.lock().unwrap(), vec!["editor panicked: action exploded"]);
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate", "editor:ping"]);
+	}
+
+	#[test]
+	fn test_window_instances_fault_separately() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let tab_log:Arc<Mutex<Vec<String>>> = log.clone();
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_profile_factory(move || FaultyProfile::new("tab", "browser.exe", &tab_log)).with_fault_threshold(Some(2));
+		system.set_active_window(&ActiveWindowInfo::new(1, "browser.exe", ""));
+		system.trigger_event("fail");
+		system.trigger_event("fail");
+
+		// Only the instance of the failing window is faulted, other windows still get a working instance.
+		system.set_active_window(&ActiveWindowInfo::new(2, "browser.exe", ""));
+		system.trigger_event("ping");
+		assert_eq!(*log.lock().unwrap(), vec!["tab:open", "tab:activate", "tab:open", "tab:activate", "tab:ping"]);
+		let statuses:Vec<bool> = system.execute_on_window_instances("tab", |instance| instance.status() == &WindowRelativeProfileStatus::Faulted);
+		assert_eq!(statuses, vec![true, false]);
+	}
+
+	#[test]
+	fn test_task_policy_panics_are_routed_to_error_handler() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut panicking_profile:FaultyProfile = FaultyProfile::new("browser", "browser.exe", &log);
+		panicking_profile.panic_on_task_policy = true;
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_profile(panicking_profile);
+		system.set_active_window(&ActiveWindowInfo::new(1, "browser.exe", ""));
+		system.set_active_window(&ActiveWindowInfo::new(2, "editor.exe", ""));
+		assert_eq!(*errors.lock().unwrap(), vec!["browser panicked: task policy exploded", "browser panicked: task policy exploded"]);
+		assert_eq!(*log.lock().unwrap(), vec!["browser:open", "browser:activate", "browser:deactivate", "editor:open", "editor:activate"]);
+		assert!(profile_status_is(&mut system, "browser", WindowRelativeProfileStatus::Deactivated));
+	}
+
+	#[test]
+	fn test_instance_factory_panics_are_routed_to_error_handler() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let errors:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let tab_log:Arc<Mutex<Vec<String>>> = log.clone();
+		let created_profiles:AtomicUsize = AtomicUsize::new(0);
+
+		// The first profile created is the template, the factory fails to create instances after that.
+		let mut system:WindowRelativeSystem = faulty_system(&log, &errors).with_profile_factory(move || {
+			if created_profiles.fetch_add(1, Ordering::SeqCst) > 0 {
+				panic!("factory exploded");
+			}
+			FaultyProfile::new("tab", "browser.exe", &tab_log)
+		});
+		system.set_active_window(&ActiveWindowInfo::new(1, "browser.exe", ""));
+		assert_eq!(*errors.lock().unwrap(), vec!["tab panicked: factory exploded"]);
+		assert!(system.window_instances("tab").is_empty());
+
+		// The system keeps handling focus changes.
+		system.set_active_window(&ActiveWindowInfo::new(2, "editor.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate"]);
+	}
+}
\ No newline at end of file
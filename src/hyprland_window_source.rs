@@ -0,0 +1,169 @@
+use crate::{ ActiveWindowInfo, ProcessWatcher, WindowEventSource, WindowGeometry, WindowRelativeSystemRemoteControl, window_info };
+use std::{ env, error::Error, io::{ BufRead, BufReader, Read, Write }, net::Shutdown, os::unix::net::UnixStream, path::{ Path, PathBuf }, sync::{ Arc, atomic::{ AtomicBool, Ordering } }, thread };
+use serde_json::Value;
+
+
+
+/// Window event source using the event socket of Hyprland.
+/// Focus changes are read from `.socket2.sock`, window details are queried from `.socket.sock`.
+#[derive(Default)]
+pub struct HyprlandWindowSource {
+	socket_directory:Option<PathBuf>,
+	event_stream:Option<UnixStream>,
+	running:Arc<AtomicBool>
+}
+impl HyprlandWindowSource {
+
+	/// Create a new Hyprland source using the instance from the environment.
+	pub fn new() -> HyprlandWindowSource {
+		HyprlandWindowSource::default()
+	}
+
+	/// Return self using the sockets in a specific directory.
+	pub fn with_socket_directory(mut self, socket_directory:&str) -> Self {
+		self.socket_directory = Some(PathBuf::from(socket_directory));
+		self
+	}
+
+	/// Get the directory containing the Hyprland sockets.
+	/// Newer versions of Hyprland keep their sockets in the runtime dir, older versions in /tmp.
+	fn socket_directory(&self) -> Result<PathBuf, Box<dyn Error>> {
+		if let Some(directory) = &self.socket_directory {
+			return Ok(directory.clone());
+		}
+		let signature:String = env::var("HYPRLAND_INSTANCE_SIGNATURE").map_err(|_| "HYPRLAND_INSTANCE_SIGNATURE is not set.")?;
+		let runtime_directory:PathBuf = env::var("XDG_RUNTIME_DIR").map(PathBuf::from).unwrap_or_default().join("hypr").join(&signature);
+		if runtime_directory.exists() {
+			Ok(runtime_directory)
+		} else {
+			Ok(PathBuf::from("/tmp/hypr").join(&signature))
+		}
+	}
+}
+impl WindowEventSource for HyprlandWindowSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+		let socket_directory:PathBuf = self.socket_directory()?;
+		let event_stream:UnixStream = UnixStream::connect(socket_directory.join(".socket2.sock"))?;
+		let request_socket:PathBuf = socket_directory.join(".socket.sock");
+
+		// A clone of the stream is kept, so stopping the source can unblock the listener thread by shutting the stream down.
+		self.event_stream = Some(event_stream.try_clone()?);
+		self.running = Arc::new(AtomicBool::new(true));
+		let running:Arc<AtomicBool> = self.running.clone();
+		let events:BufReader<UnixStream> = BufReader::new(event_stream);
+
+		// Keep listening for focus events.
+		// Every focus change sends an 'activewindow' line with class and title, followed by an 'activewindowv2' line with the address.
+		// The class and title are used as fallback when the window is already gone by the time its details are requested.
+		// Title changes are sent as 'windowtitle' lines with the address and are only forwarded for the active window.
+		// Closed windows are sent as 'closewindow' lines with the address.
+		// The window focused at startup is watched and its title changes are forwarded like those of windows focused later.
+		let process_watcher:ProcessWatcher = ProcessWatcher::start(remote.clone(), ProcessWatcher::DEFAULT_INTERVAL);
+		let mut active_address:String = String::new();
+		if let Some(window) = self.current_window() {
+			process_watcher.watch(window.pid);
+			active_address = format!("{:x}", window.window_id);
+		}
+		thread::spawn(move || {
+			let mut last_class_and_title:Option<(String, String)> = None;
+			for line in events.lines() {
+				let line:String = match line {
+					Ok(line) => line,
+					Err(error) => {
+						if running.load(Ordering::SeqCst) {
+							eprintln!("Hyprland window source lost its connection: {:?}", error);
+						}
+						break;
+					}
+				};
+				match line.split_once(">>") {
+					Some(("activewindow", class_and_title)) => {
+						last_class_and_title = class_and_title.split_once(',').map(|(class, title)| (class.to_string(), title.to_string()));
+					},
+					Some(("activewindowv2", address)) => {
+						active_address = address.trim().trim_start_matches("0x").to_string();
+						let fallback:Option<(String, String)> = last_class_and_title.take();
+						if let Some(window) = window_info_from_address(&request_socket, address, fallback) {
+							process_watcher.watch(window.pid);
+							remote.handle_window_change(&window);
+						}
+					},
+					Some(("windowtitle", address)) if address.trim().trim_start_matches("0x") == active_address => {
+						if let Some(window) = window_info_from_address(&request_socket, address, None) {
+							remote.handle_window_change(&window);
+						}
+					},
+					Some(("closewindow", address)) => {
+						if let Ok(window_id) = u64::from_str_radix(address.trim().trim_start_matches("0x"), 16) {
+							remote.handle_window_closed(window_id);
+						}
+					},
+					_ => {}
+				}
+			}
+			process_watcher.stop();
+		});
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+		if let Some(event_stream) = self.event_stream.take() {
+			let _ = event_stream.shutdown(Shutdown::Both);
+		}
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		let client:Value = query(&self.socket_directory().ok()?.join(".socket.sock"), "j/activewindow").ok()?;
+		if client["address"].is_string() { Some(window_info_from_client(&client)) } else { None }
+	}
+}
+
+
+
+/// Send a request to the Hyprland request socket and parse the JSON response.
+fn query(request_socket:&Path, command:&str) -> Result<Value, Box<dyn Error>> {
+	let mut stream:UnixStream = UnixStream::connect(request_socket)?;
+	stream.write_all(command.as_bytes())?;
+	let mut response:String = String::new();
+	stream.read_to_string(&mut response)?;
+	Ok(serde_json::from_str(&response)?)
+}
+
+/// Create a window info for the client with the given address.
+/// Event addresses are given without '0x' prefix, client addresses are given with one.
+fn window_info_from_address(request_socket:&Path, address:&str, fallback_class_and_title:Option<(String, String)>) -> Option<ActiveWindowInfo> {
+	let address:&str = address.trim().trim_start_matches("0x");
+	if address.is_empty() || address == "0" || address == "," {
+		return None;
+	}
+	let client:Option<ActiveWindowInfo> = query(request_socket, "j/clients").ok().and_then(|clients| {
+		clients.as_array()?.iter()
+			.find(|client| client["address"].as_str().map(|client_address| client_address.trim_start_matches("0x") == address).unwrap_or(false))
+			.map(window_info_from_client)
+	});
+	client.or_else(|| {
+		let (class, title) = fallback_class_and_title?;
+		Some(ActiveWindowInfo::new(u64::from_str_radix(address, 16).unwrap_or_default(), "", &title).with_class(&class))
+	})
+}
+
+/// Create a window info from a Hyprland client.
+/// The class of native Wayland clients is their app id.
+pub(crate) fn window_info_from_client(client:&Value) -> ActiveWindowInfo {
+	let pid:u32 = client["pid"].as_i64().filter(|pid| *pid > 0).unwrap_or_default() as u32;
+	let class:&str = client["class"].as_str().unwrap_or_default();
+	let window_id:u64 = client["address"].as_str().and_then(|address| u64::from_str_radix(address.trim_start_matches("0x"), 16).ok()).unwrap_or_default();
+	let window:ActiveWindowInfo = ActiveWindowInfo::new(window_id, &window_info::process_name_of_pid(pid), client["title"].as_str().unwrap_or_default())
+		.with_pid(pid)
+		.with_executable_path(window_info::executable_path_of_pid(pid))
+		.with_class(class)
+		.with_geometry(WindowGeometry::new(
+			client["at"][0].as_i64().unwrap_or_default() as i32,
+			client["at"][1].as_i64().unwrap_or_default() as i32,
+			client["size"][0].as_u64().unwrap_or_default() as u32,
+			client["size"][1].as_u64().unwrap_or_default() as u32
+		))
+		.with_monitor(&client["monitor"].as_i64().map(|monitor| monitor.to_string()).unwrap_or_default());
+	if client["xwayland"].as_bool().unwrap_or(false) { window } else { window.with_app_id(class) }
+}
\ No newline at end of file
@@ -0,0 +1,136 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, HyprlandWindowSource, TaskSystem, WindowGeometry, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, hyprland_window_source, implement_window_relative_profile_essentials, window_info };
+	use std::{ env, fs, io::{ Read, Write }, os::unix::net::UnixListener, path::PathBuf, sync::{ Arc, Mutex, mpsc::{ self, Sender } }, thread };
+	use crate::system_u::tests::poll_until;
+	use serde_json::{ Value, json };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct WindowLogProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		log:Arc<Mutex<Vec<ActiveWindowInfo>>>
+	}
+	implement_window_relative_profile_essentials!(WindowLogProfile);
+	impl WindowRelativeProfile for WindowLogProfile {
+		fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+			self.log.lock().unwrap().push(active_window.clone());
+			false
+		}
+	}
+	impl WindowLogProfile {
+		fn new(log:&Arc<Mutex<Vec<ActiveWindowInfo>>>) -> WindowLogProfile {
+			WindowLogProfile { name: "window_log", process_name: "", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), log: log.clone() }
+		}
+	}
+
+
+
+	/// Create a fake Hyprland instance that replays recorded event lines and answers client and active window queries.
+	/// Each chunk of event lines is only sent after the returned sender requests it, the first chunk is sent right away.
+	fn fake_hyprland_instance(name:&str, event_chunks:Vec<&'static str>, clients:Value, active_client:Value) -> (PathBuf, Sender<()>) {
+		let socket_directory:PathBuf = env::temp_dir().join(format!("wrs_{}_{}", name, std::process::id()));
+		let _ = fs::remove_dir_all(&socket_directory);
+		fs::create_dir_all(&socket_directory).unwrap();
+
+		// Request socket.
+		let request_listener:UnixListener = UnixListener::bind(socket_directory.join(".socket.sock")).unwrap();
+		thread::spawn(move || {
+			for mut stream in request_listener.incoming().flatten() {
+				let mut command:[u8; 64] = [0; 64];
+				let command_length:usize = stream.read(&mut command).unwrap();
+				let response:String = match &command[..command_length] {
+					b"j/clients" => clients.to_string(),
+					b"j/activewindow" => active_client.to_string(),
+					_ => "unknown request".to_string()
+				};
+				stream.write_all(response.as_bytes()).unwrap();
+			}
+		});
+
+		// Event socket, kept open until the sender is dropped.
+		let event_listener:UnixListener = UnixListener::bind(socket_directory.join(".socket2.sock")).unwrap();
+		let (chunk_sender, chunk_requests) = mpsc::channel::<()>();
+		thread::spawn(move || {
+			let (mut stream, _) = event_listener.accept().unwrap();
+			for event_chunk in event_chunks {
+				stream.write_all(event_chunk.as_bytes()).unwrap();
+				if chunk_requests.recv().is_err() {
+					return;
+				}
+			}
+			while chunk_requests.recv().is_ok() {}
+		});
+
+		(socket_directory, chunk_sender)
+	}
+
+	/// Run a system on a fake Hyprland instance and collect the windows it handles.
+	/// Every chunk of event lines should result in one window change, the next chunk is only sent once the change was handled.
+	fn collect_handled_windows(name:&str, event_chunks:Vec<&'static str>, clients:Value, active_client:Value) -> Vec<ActiveWindowInfo> {
+		let change_count:usize = event_chunks.len();
+		let (socket_directory, chunk_sender) = fake_hyprland_instance(name, event_chunks, clients, active_client);
+		let log:Arc<Mutex<Vec<ActiveWindowInfo>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowLogProfile::new(&Arc::new(Mutex::new(Vec::new()))))
+			.with_profile(WindowLogProfile::new(&log))
+			.with_event_source(HyprlandWindowSource::new().with_socket_directory(socket_directory.to_str().unwrap()));
+		for handled_changes in 1..=change_count {
+			poll_until(&mut system, |system| system.handled_window_changes() >= handled_changes);
+			let _ = chunk_sender.send(());
+		}
+		assert_eq!(system.handled_window_changes(), change_count);
+		assert_eq!(system.coalesced_window_changes(), 0);
+		let _ = fs::remove_dir_all(socket_directory);
+		log.lock().unwrap().clone()
+	}
+
+
+
+	#[test]
+	fn test_hyprland_client_mapping() {
+		let wayland_window:ActiveWindowInfo = hyprland_window_source::window_info_from_client(&json!({ "address": "0x1f", "pid": -1, "class": "kitty", "title": "Terminal", "xwayland": false, "at": [10, 20], "size": [800, 600], "monitor": 1 }));
+		assert_eq!(wayland_window, ActiveWindowInfo::new(0x1f, "", "Terminal").with_class("kitty").with_app_id("kitty").with_geometry(WindowGeometry::new(10, 20, 800, 600)).with_monitor("1"));
+		let xwayland_window:ActiveWindowInfo = hyprland_window_source::window_info_from_client(&json!({ "address": "0x2f", "pid": 0, "class": "steam", "title": "Steam", "xwayland": true }));
+		assert_eq!(xwayland_window, ActiveWindowInfo::new(0x2f, "", "Steam").with_class("steam"));
+	}
+
+	#[test]
+	fn test_hyprland_source_replays_focus_changes() {
+		let pid:u32 = std::process::id();
+		let handled_windows:Vec<ActiveWindowInfo> = collect_handled_windows(
+			"hyprland_focus",
+			vec!["workspace>>1\nactivewindow>>kitty,Terminal\nactivewindowv2>>55d2a0\n", "activewindow>>,\nactivewindowv2>>,\nactivewindow>>firefox,Closed browser\nactivewindowv2>>55d2b0\n"],
+			json!([{ "address": "0x55d2a0", "pid": pid, "class": "kitty", "title": "Terminal", "xwayland": false }]),
+			json!({})
+		);
+		assert_eq!(handled_windows, vec![
+			ActiveWindowInfo::new(0x55d2a0, &window_info::process_name_of_pid(pid), "Terminal").with_pid(pid).with_executable_path(window_info::executable_path_of_pid(pid)).with_class("kitty").with_app_id("kitty"),
+			ActiveWindowInfo::new(0x55d2b0, "", "Closed browser").with_class("firefox")
+		]);
+	}
+
+	#[test]
+	fn test_hyprland_source_forwards_title_changes_of_active_window() {
+		let pid:u32 = std::process::id();
+		let handled_windows:Vec<ActiveWindowInfo> = collect_handled_windows(
+			"hyprland_title",
+			vec!["activewindow>>kitty,Terminal\nactivewindowv2>>55d2a0\n", "windowtitle>>55d2b0\nwindowtitle>>55d2a0\n", "activewindow>>firefox,End\nactivewindowv2>>55d2c0\n"],
+			json!([{ "address": "0x55d2a0", "pid": pid, "class": "kitty", "title": "Terminal", "xwayland": false }]),
+			json!({})
+		);
+		assert_eq!(handled_windows.iter().map(|window| window.window_id).collect::<Vec<u64>>(), vec![0x55d2a0, 0x55d2a0, 0x55d2c0]);
+	}
+
+	#[test]
+	fn test_hyprland_source_forwards_title_changes_of_startup_window() {
+		let pid:u32 = std::process::id();
+		let terminal:Value = json!({ "address": "0x55d2a0", "pid": pid, "class": "kitty", "title": "Terminal", "xwayland": false });
+		let handled_windows:Vec<ActiveWindowInfo> = collect_handled_windows("hyprland_startup_title", vec!["windowtitle>>55d2b0\nwindowtitle>>55d2a0\n"], json!([terminal]), terminal.clone());
+
+		// The first window is matched when activating the startup window, the second after its title changed.
+		assert_eq!(handled_windows.iter().map(|window| window.window_id).collect::<Vec<u64>>(), vec![0x55d2a0, 0x55d2a0]);
+	}
+}
\ No newline at end of file
@@ -1,12 +1,67 @@
 mod system;
 mod system_u;
+mod clock;
+mod handler_guard;
+mod handler_guard_u;
 mod profile;
 mod profile_u;
+mod profile_error;
+mod profile_error_u;
+mod profile_task;
+mod profile_task_u;
+mod window_info;
+#[cfg(all(unix, any(feature="x11", feature="sway", feature="hyprland")))]
+mod window_info_u;
+mod window_matcher;
+mod transition;
+mod transition_u;
+mod window_matcher_u;
+mod window_event_source;
+mod process_watcher;
+mod process_watcher_u;
+mod scripted_window_source;
+mod scripted_window_source_u;
+mod polling_window_source;
+mod polling_window_source_u;
+#[cfg(all(unix, feature="x11"))]
+mod x11_window_source;
+#[cfg(all(unix, feature="x11"))]
+mod x11_window_source_u;
+#[cfg(all(unix, feature="sway"))]
+mod sway_window_source;
+#[cfg(all(unix, feature="sway"))]
+mod sway_window_source_u;
+#[cfg(all(unix, feature="hyprland"))]
+mod hyprland_window_source;
+#[cfg(all(unix, feature="hyprland"))]
+mod hyprland_window_source_u;
+#[cfg(all(windows, feature="winapi_hook"))]
 mod window_hook;
+#[cfg(all(windows, feature="winapi_hook"))]
 mod window_hook_u;
 
 pub use system::*;
+pub use clock::*;
+pub use handler_guard::HandlerPanic;
 pub use profile::*;
+pub use profile_error::{ ProfileError, ProfilePhase };
+pub use profile_task::{ ProfileTask, TaskPolicy, TaskSystemPolicy };
+pub use window_info::*;
+pub use window_matcher::WindowMatcher;
+pub use transition::*;
+pub use window_event_source::*;
+pub use process_watcher::ProcessWatcher;
+pub use scripted_window_source::*;
+pub use polling_window_source::{ FocusedWindowQuery, PollingWindowSource };
+#[cfg(all(unix, feature="x11"))]
+pub use x11_window_source::*;
+#[cfg(all(unix, feature="sway"))]
+pub use sway_window_source::SwayWindowSource;
+#[cfg(all(unix, feature="hyprland"))]
+pub use hyprland_window_source::HyprlandWindowSource;
+#[cfg(all(windows, feature="winapi_hook"))]
+pub use window_hook::WinEventHookSource;
 
+#[cfg(windows)]
 pub use window_controller::WindowController;
 pub use task_syncer::*;
\ No newline at end of file
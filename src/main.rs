@@ -1,4 +1,5 @@
-use window_relative_system::{ TaskSystem, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials };
+use window_relative_system::{ ActiveWindowInfo, TaskSystem, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials };
+#[cfg(windows)]
 use window_controller::WindowController;
 
 
@@ -11,7 +12,7 @@ struct ProfileBareBones {
 }
 implement_window_relative_profile_essentials!(ProfileBareBones);
 impl WindowRelativeProfile for ProfileBareBones {
-	fn on_activate(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+	fn on_activate(&mut self, _window:&ActiveWindowInfo) -> Result<(), Box<dyn std::error::Error>> {
 		println!("\tprofile {} was activated.", &self.name);
 		Ok(())
 	}
@@ -31,6 +32,7 @@ impl ProfileBareBones {
 
 pub fn main() {
 	let mut system:WindowRelativeSystem = WindowRelativeSystem::new(ProfileBareBones::new("default_profile", "default_process_name"));
+	#[cfg(windows)]
 	system.add_profile(ProfileBareBones::new("active_process", &WindowController::active().process_name().unwrap_or_default()));
 	system.run();
 }
\ No newline at end of file
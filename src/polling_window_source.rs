@@ -0,0 +1,141 @@
+use crate::{ ActiveWindowInfo, ProcessWatcher, WindowEventSource, WindowRelativeSystemRemoteControl };
+use std::{ error::Error, sync::{ Arc, Mutex, atomic::{ AtomicBool, Ordering } }, thread, time::{ Duration, Instant } };
+
+
+
+/// A query for the currently focused window.
+/// Used by the polling source on platforms without focus-change notifications.
+pub trait FocusedWindowQuery:Send + 'static {
+
+	/// Get the currently focused window.
+	/// Returns None if no window is focused or the focused window could not be resolved.
+	fn focused_window(&mut self) -> Option<ActiveWindowInfo>;
+}
+impl<Query:FnMut() -> Option<ActiveWindowInfo> + Send + 'static> FocusedWindowQuery for Query {
+	fn focused_window(&mut self) -> Option<ActiveWindowInfo> {
+		self()
+	}
+}
+
+
+
+/// Window event source that periodically queries the focused window.
+/// Only emits a change when the identity of the focused window differs from the last emitted one.
+/// Reports the exit of the processes of emitted windows.
+pub struct PollingWindowSource {
+	query:Arc<Mutex<Box<dyn FocusedWindowQuery>>>,
+	interval:Duration,
+	debounce:Duration,
+	running:Arc<AtomicBool>
+}
+impl PollingWindowSource {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new polling source.
+	/// Polls every 250ms without debouncing by default.
+	pub fn new<Query:FocusedWindowQuery>(query:Query) -> PollingWindowSource {
+		PollingWindowSource {
+			query: Arc::new(Mutex::new(Box::new(query))),
+			interval: Duration::from_millis(250),
+			debounce: Duration::ZERO,
+			running: Arc::new(AtomicBool::new(false))
+		}
+	}
+
+	/// Return self with a custom polling interval.
+	pub fn with_interval(mut self, interval:Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+
+	/// Return self with debouncing.
+	/// A newly focused window is only emitted after it has been focused for at least the given duration.
+	pub fn with_debounce(mut self, debounce:Duration) -> Self {
+		self.debounce = debounce;
+		self
+	}
+}
+impl WindowEventSource for PollingWindowSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+		let query:Arc<Mutex<Box<dyn FocusedWindowQuery>>> = self.query.clone();
+		let mut debouncer:FocusDebouncer = FocusDebouncer::new(self.debounce);
+		let interval:Duration = self.interval;
+
+		// Each start gets its own running flag, so a stopped polling thread can never be revived by a restart.
+		self.running.store(false, Ordering::SeqCst);
+		self.running = Arc::new(AtomicBool::new(true));
+		let running:Arc<AtomicBool> = self.running.clone();
+		let process_watcher:ProcessWatcher = ProcessWatcher::start(remote.clone(), ProcessWatcher::DEFAULT_INTERVAL);
+		thread::spawn(move || {
+			while running.load(Ordering::SeqCst) {
+				let focused_window:Option<ActiveWindowInfo> = query.lock().unwrap().focused_window();
+				if let Some(window) = debouncer.update(focused_window, Instant::now()) {
+					process_watcher.watch(window.pid);
+					remote.handle_window_change(&window);
+				}
+				thread::sleep(interval);
+			}
+			process_watcher.stop();
+		});
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		self.query.lock().unwrap().focused_window()
+	}
+}
+
+
+
+/// Decides which polled windows should be emitted as focus changes.
+pub(crate) struct FocusDebouncer {
+	debounce:Duration,
+	emitted:Option<ActiveWindowInfo>,
+	candidate:Option<(ActiveWindowInfo, Instant)>
+}
+impl FocusDebouncer {
+
+	/// Create a new debouncer.
+	pub(crate) fn new(debounce:Duration) -> FocusDebouncer {
+		FocusDebouncer {
+			debounce,
+			emitted: None,
+			candidate: None
+		}
+	}
+
+	/// Process a polled window.
+	/// Returns the window if it should be emitted as focus change.
+	pub(crate) fn update(&mut self, window:Option<ActiveWindowInfo>, now:Instant) -> Option<ActiveWindowInfo> {
+		let window:ActiveWindowInfo = window?;
+
+		// Ignore the window that was emitted last, unless its title changed.
+		if let Some(emitted) = &self.emitted && emitted.is_same_window(&window) {
+			self.candidate = None;
+			if emitted.title == window.title {
+				return None;
+			}
+			self.emitted = Some(window.clone());
+			return Some(window);
+		}
+
+		// Emit the window once it was focused long enough.
+		let focused_since:Instant = match &self.candidate {
+			Some((candidate, since)) if candidate.is_same_window(&window) => *since,
+			_ => now
+		};
+		if now.duration_since(focused_since) >= self.debounce {
+			self.candidate = None;
+			self.emitted = Some(window.clone());
+			Some(window)
+		} else {
+			self.candidate = Some((window, focused_since));
+			None
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, PollingWindowSource, TaskSystem, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials, polling_window_source::FocusDebouncer };
+	use crate::system_u::tests::poll_until;
+	use std::{ error::Error, sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct ActivationLogProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(ActivationLogProfile);
+	impl WindowRelativeProfile for ActivationLogProfile {
+		fn on_activate(&mut self, _window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(self.name.to_string());
+			Ok(())
+		}
+	}
+	impl ActivationLogProfile {
+		fn new(name:&'static str, log:&Arc<Mutex<Vec<String>>>) -> ActivationLogProfile {
+			ActivationLogProfile { name, process_name: name, task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), log: log.clone() }
+		}
+	}
+
+
+
+	#[test]
+	fn test_debouncer_only_emits_changed_identity_or_title() {
+		let now:Instant = Instant::now();
+		let mut debouncer:FocusDebouncer = FocusDebouncer::new(Duration::ZERO);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "title")), now), Some(ActiveWindowInfo::new(1, "a", "title")));
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "title")), now), None);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "changed title")), now), Some(ActiveWindowInfo::new(1, "a", "changed title")));
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "changed title")), now), None);
+		assert_eq!(debouncer.update(None, now), None);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(2, "b", "title")), now), Some(ActiveWindowInfo::new(2, "b", "title")));
+	}
+
+	#[test]
+	fn test_debouncer_waits_for_debounce_duration() {
+		let start:Instant = Instant::now();
+		let mut debouncer:FocusDebouncer = FocusDebouncer::new(Duration::from_millis(100));
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "")), start), None);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(1, "a", "")), start + Duration::from_millis(50)), None);
+
+		// A different window restarts the debounce period.
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(2, "b", "")), start + Duration::from_millis(120)), None);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(2, "b", "")), start + Duration::from_millis(200)), None);
+		assert_eq!(debouncer.update(Some(ActiveWindowInfo::new(2, "b", "")), start + Duration::from_millis(220)), Some(ActiveWindowInfo::new(2, "b", "")));
+	}
+
+	#[test]
+	fn test_polling_source_activates_profiles() {
+		let focused_window:Arc<Mutex<ActiveWindowInfo>> = Arc::new(Mutex::new(ActiveWindowInfo::new(1, "editor", "")));
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let query_window:Arc<Mutex<ActiveWindowInfo>> = focused_window.clone();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(ActivationLogProfile::new("default", &log))
+			.with_profile(ActivationLogProfile::new("editor", &log))
+			.with_profile(ActivationLogProfile::new("browser", &log))
+			.with_event_source(PollingWindowSource::new(move || Some(query_window.lock().unwrap().clone())).with_interval(Duration::from_millis(5)));
+
+		// The editor is activated on startup and reported again by the first poll.
+		poll_until(&mut system, |system| system.handled_window_changes() == 1);
+		*focused_window.lock().unwrap() = ActiveWindowInfo::new(2, "browser", "");
+		poll_until(&mut system, |system| system.handled_window_changes() == 2);
+		assert_eq!(*log.lock().unwrap(), vec!["editor", "browser"]);
+		assert_eq!(system.coalesced_window_changes(), 0);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+use crate::WindowRelativeSystemRemoteControl;
+use std::{ sync::{ Arc, Mutex, atomic::{ AtomicBool, Ordering } }, thread, time::Duration };
+
+
+
+/// Watches the processes of focused windows and reports their exit to the system.
+/// Used by window event sources, as most platforms do not send events when a process exits.
+/// Clones share the same watch list.
+#[derive(Clone)]
+pub struct ProcessWatcher {
+	pids:Arc<Mutex<Vec<u32>>>,
+	running:Arc<AtomicBool>
+}
+impl ProcessWatcher {
+
+	/// The default interval at which watched processes are checked.
+	pub const DEFAULT_INTERVAL:Duration = Duration::from_millis(500);
+
+
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Start watching processes, checking them at the given interval.
+	/// Exited processes are reported to the given remote and removed from the watch list.
+	pub fn start(remote:WindowRelativeSystemRemoteControl, interval:Duration) -> ProcessWatcher {
+		let watcher:ProcessWatcher = ProcessWatcher {
+			pids: Arc::new(Mutex::new(Vec::new())),
+			running: Arc::new(AtomicBool::new(true))
+		};
+		let pids:Arc<Mutex<Vec<u32>>> = watcher.pids.clone();
+		let running:Arc<AtomicBool> = watcher.running.clone();
+		thread::spawn(move || {
+			while running.load(Ordering::SeqCst) {
+				let mut exited_pids:Vec<u32> = Vec::new();
+				pids.lock().unwrap().retain(|pid| {
+					let alive:bool = process_is_alive(*pid);
+					if !alive {
+						exited_pids.push(*pid);
+					}
+					alive
+				});
+				for pid in exited_pids {
+					remote.handle_process_exited(pid);
+				}
+				thread::sleep(interval);
+			}
+		});
+		watcher
+	}
+
+
+
+	/* USAGE METHODS */
+
+	/// Add a process to the watch list.
+	/// Does nothing for an unknown process id of 0 or processes that are already watched.
+	pub fn watch(&self, pid:u32) {
+		let mut pids = self.pids.lock().unwrap();
+		if pid != 0 && !pids.contains(&pid) {
+			pids.push(pid);
+		}
+	}
+
+	/// Stop watching all processes.
+	pub fn stop(&self) {
+		self.running.store(false, Ordering::SeqCst);
+	}
+}
+
+
+
+/// Whether or not the process with the given id is still running.
+/// Zombie processes, which exited but were not yet reaped by their parent, are not considered running.
+/// Without procfs processes can not be checked and are always considered running.
+#[cfg(not(windows))]
+pub(crate) fn process_is_alive(pid:u32) -> bool {
+	use std::path::Path;
+
+	match std::fs::read_to_string(format!("/proc/{}/stat", pid)) {
+		Ok(stat) => !matches!(stat.rsplit_once(')').and_then(|(_, fields)| fields.trim_start().chars().next()), Some('Z') | Some('X')),
+		Err(_) => !Path::new("/proc/self").exists()
+	}
+}
+
+/// Whether or not the process with the given id is still running.
+/// Processes that cannot be inspected, like elevated processes denying access, are considered alive.
+#[cfg(windows)]
+pub(crate) fn process_is_alive(pid:u32) -> bool {
+	use winapi::{ shared::{ minwindef::DWORD, winerror::ERROR_INVALID_PARAMETER }, um::{ errhandlingapi::GetLastError, handleapi::CloseHandle, minwinbase::STILL_ACTIVE, processthreadsapi::{ GetExitCodeProcess, OpenProcess }, winnt::{ HANDLE, PROCESS_QUERY_LIMITED_INFORMATION } } };
+
+	unsafe {
+		let process:HANDLE = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+		if process.is_null() {
+			return GetLastError() != ERROR_INVALID_PARAMETER;
+		}
+		let mut exit_code:DWORD = 0;
+		let success:bool = GetExitCodeProcess(process, &mut exit_code) != 0;
+		CloseHandle(process);
+		!success || exit_code == STILL_ACTIVE
+	}
+}
\ No newline at end of file
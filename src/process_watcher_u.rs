@@ -0,0 +1,39 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, ProcessWatcher, ScriptedWindowSource, WindowRelativeProfileStatus, WindowRelativeSystem, process_watcher };
+	use crate::system_u::tests::recording_system;
+	use std::{ process::{ Child, Command }, sync::{ Arc, Mutex }, time::{ Duration, Instant } };
+
+
+
+	#[test]
+	fn test_process_is_alive() {
+		assert!(process_watcher::process_is_alive(std::process::id()));
+		assert!(!process_watcher::process_is_alive(u32::MAX));
+	}
+
+	#[cfg(target_os="linux")]
+	#[test]
+	fn test_process_watcher_closes_profile_of_exited_child() {
+		let mut child:Child = Command::new("sleep").arg("30").spawn().unwrap();
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(ActiveWindowInfo::new(1, "editor.exe", "").with_pid(child.id()));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log);
+		system.run_until_idle();
+		let watcher:ProcessWatcher = ProcessWatcher::start(system.create_remote(), Duration::from_millis(5));
+		watcher.watch(child.id());
+		system.run_for(Duration::from_millis(50));
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate"]);
+
+		// The child is not reaped, so it stays a zombie process until the end of the test.
+		child.kill().unwrap();
+		let end:Instant = Instant::now() + Duration::from_secs(5);
+		while log.lock().unwrap().len() < 4 && Instant::now() < end {
+			system.run_for(Duration::from_millis(10));
+		}
+		watcher.stop();
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate", "editor:deactivate", "editor:close", "default:open", "default:activate"]);
+		assert_eq!(system.execute_on_profile_with_name("editor", |profile| profile.status() == &WindowRelativeProfileStatus::Uninitialized), Some(true));
+		let _ = child.wait();
+	}
+}
\ No newline at end of file
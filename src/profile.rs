@@ -1,16 +1,17 @@
 use task_syncer::{ TaskScheduler, TaskSystem };
-use window_controller::WindowController;
-use std::error::Error;
+use crate::{ ActiveWindowInfo, ProfileTask, TaskSystemPolicy, TransitionContext, WindowMatcher };
+use std::{ error::Error, time::Duration };
 
 
 
-#[derive(PartialEq)]
-pub enum WindowRelativeProfileStatus { Uninitialized, Deactivated, Active }
-impl Default for WindowRelativeProfileStatus {
-	fn default() -> Self {
-		WindowRelativeProfileStatus::Uninitialized
-	}
-}
+/// The status of a profile.
+/// Faulted profiles failed too often in a row, they are not activated again until they are reset.
+#[derive(PartialEq, Default)]
+pub enum WindowRelativeProfileStatus { #[default] Uninitialized, Deactivated, Active, Faulted }
+
+/// Whether or not an event triggered on the stack of active profiles continues to lower-priority profiles.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EventPropagation { Continue, Stop }
 
 
 #[macro_export]
@@ -77,10 +78,43 @@ pub trait WindowRelativeProfile:WindowRelativeProfileEssentials {
 
 	/* PROPERTY GETTER METHODS */
 
+	/// The matcher describing the windows this profile applies to, or None to match windows using `matches_window`.
+	/// The system builds the matcher once when the profile is added. Only declared matchers are described, checked for shadowing and deliver title captures.
+	/// Named captures of title regexes are available to the handlers through `ActiveWindowInfo::title_capture`.
+	fn matcher(&self) -> Option<WindowMatcher> {
+		None
+	}
+
 	/// Whether or not this profile is the active one.
-	#[allow(unused_variables)]
-	fn matches_window(&self, active_window:&WindowController, active_process_name:&str, active_process_title:&str) -> bool {
-		self.process_name() == active_process_name
+	/// Only called by the system for profiles that do not declare a matcher. Defaults to an exact match on the process name of the profile.
+	fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+		WindowMatcher::process_name(self.process_name()).matches(active_window)
+	}
+
+	/// The priority of this profile.
+	/// When multiple profiles match a window, the one with the highest priority is activated.
+	/// Read once when the profile is added to the system.
+	fn priority(&self) -> i32 {
+		0
+	}
+
+	/// Whether or not this profile is an overlay.
+	/// Overlay profiles are activated alongside the profile of the window whenever they match, without replacing it.
+	/// Read once when the profile is added to the system.
+	fn is_overlay(&self) -> bool {
+		false
+	}
+
+	/// How long a window has to keep focus before this profile is activated for it.
+	/// Keeps the profile from activating for windows that are only focused briefly.
+	fn activation_delay(&self) -> Duration {
+		Duration::ZERO
+	}
+
+	/// How long this profile stays active after its window lost focus.
+	/// Keeps the profile from deactivating when focus quickly returns, for example after an alt-tab or notification.
+	fn deactivation_grace(&self) -> Duration {
+		Duration::ZERO
 	}
 
 	/// Get the task scheduler of this profile.
@@ -88,28 +122,91 @@ pub trait WindowRelativeProfile:WindowRelativeProfileEssentials {
 		self.task_system().scheduler()
 	}
 
+	/// The policy deciding when the system lets the `TaskSystem` of this profile run.
+	fn task_policy(&self) -> TaskSystemPolicy {
+		TaskSystemPolicy::RunWhileActive
+	}
+
+	/// Start or stop the `TaskSystem` of this profile.
+	/// Called by the system whenever the status of the profile changes, following `task_policy`.
+	fn set_task_system_running(&mut self, running:bool) {
+		if running {
+			self.task_system_mut().start();
+		} else {
+			self.task_system_mut().stop();
+		}
+	}
+
+	/// Get the repeating tasks of this profile.
+	/// The system starts, pauses and stops each task on status changes according to the policy of the task.
+	/// Unlike the tasks of the `TaskSystem`, these can pause and resume their interval and each choose their own policy.
+	fn tasks(&self) -> &[ProfileTask] {
+		&[]
+	}
+
 
 
 	/* HANDLER METHODS */
 
 	/// Executes when the profile is initially opened.
-	fn on_open(&mut self) -> Result<(), Box<dyn Error>> { Ok(()) }
+	#[allow(unused_variables)]
+	fn on_open(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
 
 	/// Executes when the profile is activated.
-	fn on_activate(&mut self) -> Result<(), Box<dyn Error>> { Ok(()) }
+	#[allow(unused_variables)]
+	fn on_activate(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
 
 	/// Executes when the profile is deactivated.
-	fn on_deactivate(&mut self) -> Result<(), Box<dyn Error>> { Ok(()) }
+	/// The given window is the one that was active while the profile was.
+	#[allow(unused_variables)]
+	fn on_deactivate(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
+
+	/// Executes when the profile is activated or deactivated, before the according handler.
+	/// The context tells why the transition happened and which profiles and windows are involved.
+	#[allow(unused_variables)]
+	fn on_transition(&mut self, transition:&TransitionContext) -> Result<(), Box<dyn Error>> { Ok(()) }
+
+	/// Executes when the profile stays active while another window receives focus.
+	#[allow(unused_variables)]
+	fn on_window_changed(&mut self, previous_window:&ActiveWindowInfo, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
+
+	/// Executes when the title of the focused window changes while the profile is active.
+	#[allow(unused_variables)]
+	fn on_title_changed(&mut self, previous_window:&ActiveWindowInfo, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
+
+	/// Executes when an opened profile is closed, for example when the system stops.
+	#[allow(unused_variables)]
+	fn on_close(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> { Ok(()) }
 
 	/// Execute a named event.
-	fn execute_event(&mut self, window:&WindowController, event_name:&str) -> Result<(), Box<dyn Error>> {
+	fn execute_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+		self.on_event(window, event_name)
+	}
+
+	/// Execute a named event triggered on the stack of active profiles.
+	/// Return `EventPropagation::Stop` to keep the event from reaching lower-priority profiles.
+	fn execute_layered_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<EventPropagation, Box<dyn Error>> {
+		self.execute_event(window, event_name).map(|_| EventPropagation::Continue)
+	}
+
+	/// Executes when the 'activate' or 'deactivate' event is triggered by a transition.
+	/// Defaults to `on_event`, override to receive the context of the transition as well.
+	#[allow(unused_variables)]
+	fn on_transition_event(&mut self, window:&ActiveWindowInfo, event_name:&str, transition:&TransitionContext) -> Result<(), Box<dyn Error>> {
 		self.on_event(window, event_name)
 	}
 
+	/// Executes when the title captures of the focused window change while the profile stays active.
+	/// Defaults to `on_event` with the 'title_captures_changed' event, override to handle the captures directly.
+	fn on_title_captures_changed(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+		self.on_event(window, "title_captures_changed")
+	}
+
 	/// Executes when any named event is triggered.
-	/// Includes the 'open', 'activate' and 'deactivate' events.
+	/// Includes the 'open', 'activate', 'window_changed', 'title_changed', 'deactivate' and 'close' events.
+	/// Includes the 'title_captures_changed' event when the title captures change while the profile stays active.
 	#[allow(unused_variables)]
-	fn on_event(&mut self, window:&WindowController, event_name:&str) -> Result<(), Box<dyn Error>> {
+	fn on_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
 		Ok(())
 	}
 }
@@ -117,11 +214,11 @@ pub trait WindowRelativeProfile:WindowRelativeProfileEssentials {
 
 
 impl<T:WindowRelativeProfileEssentials> WindowRelativeProfileEssentials for Box<T> {
-	fn name(&self) -> &str { (&**self).name() }
-	fn process_name(&self) -> &str { (&**self).process_name() }
-	fn task_system(&self) -> &TaskSystem { (&**self).task_system() }
-	fn task_system_mut(&mut self) -> &mut TaskSystem { (&mut **self).task_system_mut() }
-	fn status(&self) -> &WindowRelativeProfileStatus { (&**self).status() }
-	fn status_mut(&mut self) -> &mut WindowRelativeProfileStatus { (&mut **self).status_mut() }
+	fn name(&self) -> &str { (**self).name() }
+	fn process_name(&self) -> &str { (**self).process_name() }
+	fn task_system(&self) -> &TaskSystem { (**self).task_system() }
+	fn task_system_mut(&mut self) -> &mut TaskSystem { (**self).task_system_mut() }
+	fn status(&self) -> &WindowRelativeProfileStatus { (**self).status() }
+	fn status_mut(&mut self) -> &mut WindowRelativeProfileStatus { (**self).status_mut() }
 }
 impl<T:WindowRelativeProfile> WindowRelativeProfile for Box<T> {}
\ No newline at end of file
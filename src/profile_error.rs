@@ -0,0 +1,110 @@
+use crate::ActiveWindowInfo;
+use std::{ error::Error, fmt::{ self, Display, Formatter }, time::SystemTime };
+
+
+
+/// The handler of a profile in which an error occurred.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProfilePhase {
+
+	/// Opening the profile, `on_open` or the 'open' event.
+	Open,
+
+	/// The `on_transition` handler, before activating or deactivating the profile.
+	Transition,
+
+	/// Activating the profile, `on_activate` or the 'activate' event.
+	Activate,
+
+	/// Another window received focus while the profile stayed active, `on_window_changed` or the 'window_changed' event.
+	WindowChanged,
+
+	/// The title of the focused window changed while the profile stayed active, `on_title_changed`, `on_title_captures_changed` or the 'title_changed' and 'title_captures_changed' events.
+	TitleChanged,
+
+	/// Deactivating the profile, `on_deactivate` or the 'deactivate' event.
+	Deactivate,
+
+	/// Closing the profile, `on_close` or the 'close' event.
+	Close,
+
+	/// An event triggered on the system.
+	Event,
+
+	/// An event triggered through a remote.
+	RemoteEvent,
+
+	/// Matching a window using `matches_window`.
+	Match,
+
+	/// Reading a property of the profile, like its matcher, priority or activation delay.
+	Properties,
+
+	/// An action given to one of the `execute_on` methods of a remote.
+	RemoteAction
+}
+impl Display for ProfilePhase {
+	fn fmt(&self, f:&mut Formatter<'_>) -> fmt::Result {
+		let name:&str = match self {
+			ProfilePhase::Open => "open",
+			ProfilePhase::Transition => "transition",
+			ProfilePhase::Activate => "activate",
+			ProfilePhase::WindowChanged => "window_changed",
+			ProfilePhase::TitleChanged => "title_changed",
+			ProfilePhase::Deactivate => "deactivate",
+			ProfilePhase::Close => "close",
+			ProfilePhase::Event => "event",
+			ProfilePhase::RemoteEvent => "remote_event",
+			ProfilePhase::Match => "match",
+			ProfilePhase::Properties => "properties",
+			ProfilePhase::RemoteAction => "remote_action"
+		};
+		write!(f, "{}", name)
+	}
+}
+
+
+
+/// An error returned or panic raised by a handler of a profile, given to the error handler of the system.
+#[derive(Debug)]
+pub struct ProfileError {
+	pub profile_name:String,
+	pub phase:ProfilePhase,
+
+	/// The name of the event the error occurred in, None if it occurred in a dedicated handler like `on_activate`.
+	pub event_name:Option<String>,
+
+	/// The window given to the failing handler.
+	pub window:ActiveWindowInfo,
+	pub timestamp:SystemTime,
+	pub error:Box<dyn Error>
+}
+impl ProfileError {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new profile error, timestamped at the current time.
+	pub fn new(profile_name:&str, phase:ProfilePhase, event_name:Option<&str>, window:&ActiveWindowInfo, error:Box<dyn Error>) -> ProfileError {
+		ProfileError {
+			profile_name: profile_name.to_string(),
+			phase,
+			event_name: event_name.map(|event_name| event_name.to_string()),
+			window: window.clone(),
+			timestamp: SystemTime::now(),
+			error
+		}
+	}
+}
+impl Display for ProfileError {
+	fn fmt(&self, f:&mut Formatter<'_>) -> fmt::Result {
+		match &self.event_name {
+			Some(event_name) => write!(f, "profile {} failed in {} (event '{}'): {}", self.profile_name, self.phase, event_name, self.error),
+			None => write!(f, "profile {} failed in {}: {}", self.profile_name, self.phase, self.error)
+		}
+	}
+}
+impl Error for ProfileError {
+	fn source(&self) -> Option<&(dyn Error + 'static)> {
+		Some(&*self.error)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,90 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, HandlerPanic, ProfileError, ProfilePhase, TaskSystem, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, WindowRelativeSystemRemoteControl, implement_window_relative_profile_essentials };
+	use std::{ error::Error, sync::{ Arc, Mutex } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+
+	/// The profile name, phase, event name, window id and message of an error received by the handler.
+	type RecordedError = (String, ProfilePhase, Option<String>, u64, String);
+
+
+
+	struct FailingProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus
+	}
+	implement_window_relative_profile_essentials!(FailingProfile);
+	impl WindowRelativeProfile for FailingProfile {
+		fn on_open(&mut self, _window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+			Err("open failed".into())
+		}
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			match event_name {
+				"deactivate" => Err("deactivate event failed".into()),
+				"remote" => panic!("remote event exploded"),
+				_ => Ok(())
+			}
+		}
+	}
+	impl FailingProfile {
+		fn new(name:&'static str, process_name:&'static str) -> FailingProfile {
+			FailingProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default()
+			}
+		}
+	}
+
+
+
+	#[test]
+	fn test_profile_errors_describe_their_origin() {
+		let errors:Arc<Mutex<Vec<RecordedError>>> = Arc::new(Mutex::new(Vec::new()));
+		let handler_errors:Arc<Mutex<Vec<RecordedError>>> = errors.clone();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(FailingProfile::new("default", ""))
+			.with_profile(FailingProfile::new("editor", "editor.exe"))
+			.with_profile_error_handler(move |error| {
+				let message:String = match error.error.downcast_ref::<HandlerPanic>() {
+					Some(panic) => panic.message.clone(),
+					None => error.error.to_string()
+				};
+				handler_errors.lock().unwrap().push((error.profile_name, error.phase, error.event_name, error.window.window_id, message));
+			})
+			.with_startup_activation(false);
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		remote.handle_window_change(&ActiveWindowInfo::new(1, "editor.exe", "main.rs"));
+		remote.trigger_event("remote");
+		system.run_until_idle();
+		system.trigger_event("remote");
+		remote.handle_window_change(&ActiveWindowInfo::new(2, "other.exe", ""));
+		system.run_until_idle();
+
+		let errors = errors.lock().unwrap();
+		let origins:Vec<(&str, ProfilePhase, Option<&str>, u64, &str)> = errors.iter().map(|(profile_name, phase, event_name, window_id, message)| (profile_name.as_str(), *phase, event_name.as_deref(), *window_id, message.as_str())).collect();
+		assert_eq!(origins, vec![
+			("editor", ProfilePhase::Open, None, 1, "open failed"),
+			("editor", ProfilePhase::RemoteEvent, Some("remote"), 1, "remote event exploded"),
+			("editor", ProfilePhase::Event, Some("remote"), 1, "remote event exploded"),
+			("editor", ProfilePhase::Deactivate, Some("deactivate"), 1, "deactivate event failed"),
+			("default", ProfilePhase::Open, None, 2, "open failed")
+		]);
+	}
+
+	#[test]
+	fn test_profile_error_display() {
+		let window:ActiveWindowInfo = ActiveWindowInfo::new(1, "editor.exe", "");
+		let error:ProfileError = ProfileError::new("editor", ProfilePhase::Deactivate, Some("deactivate"), &window, "disk full".into());
+		assert_eq!(error.to_string(), "profile editor failed in deactivate (event 'deactivate'): disk full");
+		assert_eq!(error.source().map(|source| source.to_string()), Some("disk full".to_string()));
+		let error:ProfileError = ProfileError::new("editor", ProfilePhase::WindowChanged, None, &window, "lost track".into());
+		assert_eq!(error.to_string(), "profile editor failed in window_changed: lost track");
+		let error:ProfileError = ProfileError::new("editor", ProfilePhase::RemoteEvent, Some("save"), &window, "disk full".into());
+		assert_eq!(error.to_string(), "profile editor failed in remote_event (event 'save'): disk full");
+	}
+}
\ No newline at end of file
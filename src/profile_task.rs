@@ -0,0 +1,260 @@
+use crate::{ Clock, SystemClock, WindowRelativeProfileStatus };
+use std::{ sync::{ Arc, Condvar, Mutex, MutexGuard }, thread, time::{ Duration, Instant } };
+
+
+
+type TaskAction = Box<dyn FnMut() + Send + 'static>;
+
+
+
+/// Decides when the system lets a task of a profile run.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TaskPolicy {
+
+	/// Only run while the profile is active, restarting the interval on every activation.
+	#[default]
+	RunWhileActive,
+
+	/// Only run while the profile is active, resuming the interval where it was paused on deactivation.
+	PauseWhileInactive,
+
+	/// Run from the moment the profile is opened until it is closed.
+	AlwaysRun
+}
+impl TaskPolicy {
+
+	/// Whether or not tasks with this policy run while their profile has the given status.
+	pub(crate) fn runs_with_status(&self, status:&WindowRelativeProfileStatus) -> bool {
+		matches!((status, self), (WindowRelativeProfileStatus::Active, _) | (WindowRelativeProfileStatus::Deactivated, TaskPolicy::AlwaysRun))
+	}
+}
+
+
+
+/// Decides when the system lets the `TaskSystem` of a profile run.
+/// The `TaskSystem` can only be started and stopped as a whole, so unlike `TaskPolicy` it cannot pause while inactive.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TaskSystemPolicy {
+
+	/// Only run while the profile is active.
+	#[default]
+	RunWhileActive,
+
+	/// Run from the moment the profile is opened until it is closed.
+	AlwaysRun
+}
+impl TaskSystemPolicy {
+
+	/// Whether or not a task system with this policy runs while its profile has the given status.
+	pub(crate) fn runs_with_status(&self, status:&WindowRelativeProfileStatus) -> bool {
+		TaskPolicy::from(*self).runs_with_status(status)
+	}
+}
+impl From<TaskSystemPolicy> for TaskPolicy {
+	fn from(policy:TaskSystemPolicy) -> TaskPolicy {
+		match policy {
+			TaskSystemPolicy::RunWhileActive => TaskPolicy::RunWhileActive,
+			TaskSystemPolicy::AlwaysRun => TaskPolicy::AlwaysRun
+		}
+	}
+}
+
+
+
+/// The state of a task shared with its runner thread.
+struct TaskState {
+	running:bool,
+	generation:usize,
+	progress:Duration,
+	waiting_since:Instant,
+	run_count:usize
+}
+
+
+
+/// A repeating task of a profile.
+/// The system starts, pauses and stops the task when the status of the profile changes, according to the policy of the task.
+pub struct ProfileTask {
+	name:String,
+	interval:Duration,
+	policy:TaskPolicy,
+	clock:Arc<dyn Clock>,
+	action:Arc<Mutex<TaskAction>>,
+	state:Arc<(Mutex<TaskState>, Condvar)>
+}
+impl ProfileTask {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new task executing the given action every interval.
+	/// Only runs while the profile is active by default.
+	pub fn new<Action:FnMut() + Send + 'static>(name:&str, interval:Duration, action:Action) -> ProfileTask {
+		ProfileTask {
+			name: name.to_string(),
+			interval,
+			policy: TaskPolicy::default(),
+			clock: Arc::new(SystemClock),
+			action: Arc::new(Mutex::new(Box::new(action))),
+			state: Arc::new((Mutex::new(TaskState { running: false, generation: 0, progress: Duration::ZERO, waiting_since: Instant::now(), run_count: 0 }), Condvar::new()))
+		}
+	}
+
+	/// Return self with a policy.
+	pub fn with_policy(mut self, policy:TaskPolicy) -> Self {
+		self.set_policy(policy);
+		self
+	}
+
+	/// Set the policy deciding when the task runs.
+	/// Takes effect on the next status change of the profile.
+	pub fn set_policy(&mut self, policy:TaskPolicy) {
+		self.policy = policy;
+	}
+
+	/// Return self with a custom clock.
+	pub fn with_clock<CustomClock:Clock>(mut self, clock:CustomClock) -> Self {
+		self.set_clock(clock);
+		self
+	}
+
+	/// Set the clock used to time the interval of the task.
+	/// Allows tests to use a `FakeClock` and execute the task with `run_if_due`.
+	pub fn set_clock<CustomClock:Clock>(&mut self, clock:CustomClock) {
+		self.clock = Arc::new(clock);
+	}
+
+
+
+	/* PROPERTY GETTER METHODS */
+
+	/// Get the name of the task.
+	pub fn name(&self) -> &str {
+		&self.name
+	}
+
+	/// Get the interval of the task.
+	pub fn interval(&self) -> Duration {
+		self.interval
+	}
+
+	/// Get the policy of the task.
+	pub fn policy(&self) -> TaskPolicy {
+		self.policy
+	}
+
+	/// Whether or not the task is currently running.
+	pub fn is_running(&self) -> bool {
+		self.state().running
+	}
+
+	/// Get the amount of times the action of the task was executed.
+	pub fn run_count(&self) -> usize {
+		self.state().run_count
+	}
+
+	/// Lock the state of the task.
+	fn state(&self) -> MutexGuard<'_, TaskState> {
+		self.state.0.lock().unwrap()
+	}
+
+
+
+	/* STATE CHANGING METHODS */
+
+	/// Start, pause or stop the task according to its policy and the given status of its profile.
+	pub(crate) fn apply_profile_status(&self, status:&WindowRelativeProfileStatus) {
+		if self.policy.runs_with_status(status) {
+			self.run();
+		} else {
+			self.halt(*status == WindowRelativeProfileStatus::Deactivated && self.policy == TaskPolicy::PauseWhileInactive);
+		}
+	}
+
+	/// Execute the action if the task is running and its interval has passed according to its clock.
+	/// Waits for an execution already started by the runner of the task, so its effects are visible once this returns.
+	pub fn run_if_due(&self) {
+		let mut state:MutexGuard<'_, TaskState> = self.state();
+		let due:bool = state.running && take_due_run(&mut state, self.interval, &*self.clock).is_none();
+		let mut action:MutexGuard<'_, TaskAction> = self.action.lock().unwrap();
+		drop(state);
+		if due {
+			action();
+		}
+	}
+
+	/// Start running the task, continuing the progress of the interval it was halted in.
+	/// Does nothing if the task is already running.
+	fn run(&self) {
+		let mut state:MutexGuard<'_, TaskState> = self.state();
+		if state.running {
+			return;
+		}
+		state.running = true;
+		state.generation += 1;
+		state.waiting_since = self.clock.now();
+
+		// The runner thread exits as soon as the task is halted, which changes the generation.
+		let generation:usize = state.generation;
+		let interval:Duration = self.interval;
+		let clock:Arc<dyn Clock> = self.clock.clone();
+		let action:Arc<Mutex<TaskAction>> = self.action.clone();
+		let shared_state:Arc<(Mutex<TaskState>, Condvar)> = self.state.clone();
+		thread::spawn(move || {
+			let (state_lock, halted) = &*shared_state;
+			let mut state:MutexGuard<'_, TaskState> = state_lock.lock().unwrap();
+			while state.generation == generation {
+				match take_due_run(&mut state, interval, &*clock) {
+					None => {
+
+						// The action is locked before the state is released, so `run_if_due` waits for this execution.
+						let mut action:MutexGuard<'_, TaskAction> = action.lock().unwrap();
+						drop(state);
+						action();
+						drop(action);
+						state = state_lock.lock().unwrap();
+					},
+					Some(remaining) => state = halted.wait_timeout(state, remaining).unwrap().0
+				}
+			}
+		});
+	}
+
+	/// Stop running the task.
+	/// When keeping progress, the next run continues the interval instead of starting a new one.
+	fn halt(&self, keep_progress:bool) {
+		let mut state:MutexGuard<'_, TaskState> = self.state();
+		if state.running {
+			state.running = false;
+			state.generation += 1;
+			self.state.1.notify_all();
+			if keep_progress {
+				state.progress = (state.progress + self.clock.now().saturating_duration_since(state.waiting_since)).min(self.interval);
+			}
+		}
+		if !keep_progress {
+			state.progress = Duration::ZERO;
+		}
+	}
+}
+impl Drop for ProfileTask {
+	fn drop(&mut self) {
+		self.halt(false);
+	}
+}
+
+
+
+/// Start a new interval and count the run if the interval of a task has passed.
+/// Returns the time remaining until the interval passes otherwise.
+fn take_due_run(state:&mut TaskState, interval:Duration, clock:&dyn Clock) -> Option<Duration> {
+	let now:Instant = clock.now();
+	let remaining:Duration = interval.saturating_sub(state.progress).saturating_sub(now.saturating_duration_since(state.waiting_since));
+	if remaining.is_zero() {
+		state.progress = Duration::ZERO;
+		state.waiting_since = now;
+		state.run_count += 1;
+		None
+	} else {
+		Some(remaining)
+	}
+}
\ No newline at end of file
@@ -0,0 +1,198 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, FakeClock, ProfileTask, TaskPolicy, TaskSystem, TaskSystemPolicy, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials };
+	use std::{ sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering } }, time::Duration };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct TaskProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		tasks:Vec<ProfileTask>
+	}
+	implement_window_relative_profile_essentials!(TaskProfile);
+	impl WindowRelativeProfile for TaskProfile {
+		fn tasks(&self) -> &[ProfileTask] {
+			&self.tasks
+		}
+	}
+	impl TaskProfile {
+		fn new(name:&'static str, process_name:&'static str, tasks:Vec<ProfileTask>) -> TaskProfile {
+			TaskProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default(),
+				tasks
+			}
+		}
+	}
+
+
+
+	struct TaskSystemProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		task_policy:TaskSystemPolicy,
+		task_system_log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(TaskSystemProfile);
+	impl WindowRelativeProfile for TaskSystemProfile {
+		fn task_policy(&self) -> TaskSystemPolicy {
+			self.task_policy
+		}
+		fn set_task_system_running(&mut self, running:bool) {
+			self.task_system_log.lock().unwrap().push(format!("{}:{}", self.name, if running { "start" } else { "stop" }));
+			if running {
+				self.task_system.start();
+			} else {
+				self.task_system.stop();
+			}
+		}
+	}
+	impl TaskSystemProfile {
+		fn new(name:&'static str, process_name:&'static str, task_policy:TaskSystemPolicy, task_system_log:&Arc<Mutex<Vec<String>>>) -> TaskSystemProfile {
+			TaskSystemProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default(),
+				task_policy,
+				task_system_log: task_system_log.clone()
+			}
+		}
+	}
+
+
+
+	const INTERVAL:Duration = Duration::from_secs(60);
+
+	fn run_counts(system:&mut WindowRelativeSystem, profile_name:&str) -> Vec<usize> {
+		system.execute_on_profile_with_name(profile_name, |profile| profile.tasks().iter().map(|task| task.run_count()).collect()).unwrap()
+	}
+
+	fn run_due_tasks(system:&mut WindowRelativeSystem, profile_name:&str) {
+		system.execute_on_profile_with_name(profile_name, |profile| profile.tasks().iter().for_each(|task| task.run_if_due())).unwrap();
+	}
+
+
+
+	#[test]
+	fn test_task_policies_follow_profile_status() {
+		let tasks:Vec<ProfileTask> = [TaskPolicy::RunWhileActive, TaskPolicy::PauseWhileInactive, TaskPolicy::AlwaysRun].into_iter().map(|policy| ProfileTask::new("task", INTERVAL, || {}).with_policy(policy)).collect();
+		let running = |tasks:&[ProfileTask]| tasks.iter().map(|task| task.is_running()).collect::<Vec<bool>>();
+		for task in &tasks {
+			task.apply_profile_status(&WindowRelativeProfileStatus::Active);
+		}
+		assert_eq!(running(&tasks), vec![true, true, true]);
+		for task in &tasks {
+			task.apply_profile_status(&WindowRelativeProfileStatus::Deactivated);
+		}
+		assert_eq!(running(&tasks), vec![false, false, true]);
+		for task in &tasks {
+			task.apply_profile_status(&WindowRelativeProfileStatus::Uninitialized);
+		}
+		assert_eq!(running(&tasks), vec![false, false, false]);
+	}
+
+	#[test]
+	fn test_task_policies_decide_when_tasks_run() {
+		let policies:[TaskPolicy; 3] = [TaskPolicy::RunWhileActive, TaskPolicy::PauseWhileInactive, TaskPolicy::AlwaysRun];
+		let runs_with_status = |status:WindowRelativeProfileStatus| policies.iter().map(|policy| policy.runs_with_status(&status)).collect::<Vec<bool>>();
+		assert_eq!(runs_with_status(WindowRelativeProfileStatus::Active), vec![true, true, true]);
+		assert_eq!(runs_with_status(WindowRelativeProfileStatus::Deactivated), vec![false, false, true]);
+		assert_eq!(runs_with_status(WindowRelativeProfileStatus::Uninitialized), vec![false, false, false]);
+		assert_eq!(runs_with_status(WindowRelativeProfileStatus::Faulted), vec![false, false, false]);
+		assert_eq!(TaskProfile::new("editor", "editor.exe", Vec::new()).task_policy(), TaskSystemPolicy::RunWhileActive);
+	}
+
+	#[test]
+	fn test_task_runs_when_clock_passes_interval() {
+		let clock:FakeClock = FakeClock::new();
+		let task:ProfileTask = ProfileTask::new("task", INTERVAL, || {}).with_clock(clock.clone());
+		task.run_if_due();
+		assert_eq!(task.run_count(), 0);
+		task.apply_profile_status(&WindowRelativeProfileStatus::Active);
+		clock.advance(INTERVAL - Duration::from_secs(1));
+		task.run_if_due();
+		assert_eq!(task.run_count(), 0);
+		clock.advance(Duration::from_secs(1));
+		task.run_if_due();
+		task.run_if_due();
+		assert_eq!(task.run_count(), 1);
+		clock.advance(INTERVAL);
+		task.run_if_due();
+		assert_eq!(task.run_count(), 2);
+	}
+
+	#[test]
+	fn test_paused_task_resumes_interval() {
+		let clock:FakeClock = FakeClock::new();
+		let paused:ProfileTask = ProfileTask::new("paused", INTERVAL, || {}).with_policy(TaskPolicy::PauseWhileInactive).with_clock(clock.clone());
+		let restarted:ProfileTask = ProfileTask::new("restarted", INTERVAL, || {}).with_clock(clock.clone());
+		for _ in 0..3 {
+			paused.apply_profile_status(&WindowRelativeProfileStatus::Active);
+			restarted.apply_profile_status(&WindowRelativeProfileStatus::Active);
+			clock.advance(INTERVAL * 9 / 20);
+			paused.run_if_due();
+			restarted.run_if_due();
+			paused.apply_profile_status(&WindowRelativeProfileStatus::Deactivated);
+			restarted.apply_profile_status(&WindowRelativeProfileStatus::Deactivated);
+
+			// Time passing while deactivated does not count towards the interval.
+			clock.advance(INTERVAL);
+		}
+		assert_eq!(paused.run_count(), 1);
+		assert_eq!(restarted.run_count(), 0);
+	}
+
+	#[test]
+	fn test_auto_save_stops_when_profile_loses_focus() {
+		let clock:FakeClock = FakeClock::new();
+		let saves:Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+		let task_saves:Arc<AtomicUsize> = saves.clone();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(TaskProfile::new("default", "default.exe", Vec::new()))
+			.with_profile(TaskProfile::new("editor", "editor.exe", vec![
+				ProfileTask::new("auto_save", INTERVAL, move || { task_saves.fetch_add(1, Ordering::SeqCst); }).with_clock(clock.clone()),
+				ProfileTask::new("sync", INTERVAL, || {}).with_policy(TaskPolicy::AlwaysRun).with_clock(clock.clone())
+			]));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		clock.advance(INTERVAL);
+		run_due_tasks(&mut system, "editor");
+		assert_eq!(saves.load(Ordering::SeqCst), 1);
+		assert_eq!(run_counts(&mut system, "editor"), vec![1, 1]);
+
+		// Once the editor loses focus only the always running task keeps running.
+		system.set_active_window(&ActiveWindowInfo::new(2, "browser.exe", ""));
+		clock.advance(INTERVAL);
+		run_due_tasks(&mut system, "editor");
+		assert_eq!(saves.load(Ordering::SeqCst), 1);
+		assert_eq!(run_counts(&mut system, "editor"), vec![1, 2]);
+
+		// Refocusing the editor starts saving again, restarting the interval.
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		run_due_tasks(&mut system, "editor");
+		assert_eq!(saves.load(Ordering::SeqCst), 1);
+		clock.advance(INTERVAL);
+		run_due_tasks(&mut system, "editor");
+		assert_eq!(saves.load(Ordering::SeqCst), 2);
+	}
+
+	#[test]
+	fn test_task_system_stops_when_profile_loses_focus() {
+		let task_system_log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(TaskSystemProfile::new("default", "default.exe", TaskSystemPolicy::RunWhileActive, &task_system_log))
+			.with_profile(TaskSystemProfile::new("editor", "editor.exe", TaskSystemPolicy::RunWhileActive, &task_system_log))
+			.with_profile(TaskSystemProfile::new("player", "player.exe", TaskSystemPolicy::AlwaysRun, &task_system_log));
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		system.set_active_window(&ActiveWindowInfo::new(2, "player.exe", ""));
+		system.set_active_window(&ActiveWindowInfo::new(3, "browser.exe", ""));
+
+		// The task system of the player keeps running after it loses focus.
+		assert_eq!(*task_system_log.lock().unwrap(), vec!["editor:start", "editor:stop", "player:start", "player:start", "default:start"]);
+	}
+}
\ No newline at end of file
@@ -1,7 +1,6 @@
 #[cfg(test)]
 mod tests {
-	use crate::{ WindowRelativeProfile, window_relative_profile };
-	use window_controller::WindowController;
+	use crate::{ ActiveWindowInfo, WindowRelativeProfile, window_relative_profile };
 	use std::{ error::Error, sync::Mutex };
 	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
 	
@@ -13,7 +12,7 @@ mod tests {
 
 	window_relative_profile!(TestProfile, "test_profile", "test_process_name.exe");
 	impl WindowRelativeProfile for TestProfile {
-		fn on_event(&mut self, _window:&WindowController, event_name:&str) -> Result<(), Box<dyn Error>> {
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
 			if event_name == "custom_event_tag" {
 				*EVENT_RUN_PROOF.lock().unwrap() = 1;
 			}
@@ -26,9 +25,9 @@ mod tests {
 	fn can_use_test_profile() {
 		let mut profile:TestProfile = TestProfile::default();
 		assert_eq!(*EVENT_RUN_PROOF.lock().unwrap(), 0);
-		profile.execute_event(&WindowController::active(), "event_name").unwrap();
+		profile.execute_event(&ActiveWindowInfo::default(), "event_name").unwrap();
 		assert_eq!(*EVENT_RUN_PROOF.lock().unwrap(), 0);
-		profile.execute_event(&WindowController::active(), "custom_event_tag").unwrap();
+		profile.execute_event(&ActiveWindowInfo::default(), "custom_event_tag").unwrap();
 		assert_eq!(*EVENT_RUN_PROOF.lock().unwrap(), 1);
 	}
 }
\ No newline at end of file
@@ -0,0 +1,93 @@
+use crate::{ ActiveWindowInfo, WindowEventSource, WindowRelativeSystemRemoteControl };
+use std::{ error::Error, sync::{ Arc, Mutex, MutexGuard, mpsc }, time::Duration };
+
+
+
+type ScriptedStep = Box<dyn FnOnce(&WindowRelativeSystemRemoteControl) + Send + 'static>;
+
+
+
+/// Window event source driven by scripted focus steps instead of the OS.
+/// Clones share the same script, so a test can keep a clone to push steps after handing the source to the system.
+#[derive(Clone, Default)]
+pub struct ScriptedWindowSource {
+	pending_steps:Arc<Mutex<Vec<ScriptedStep>>>,
+	remote:Arc<Mutex<Option<WindowRelativeSystemRemoteControl>>>,
+	current_window:Arc<Mutex<Option<ActiveWindowInfo>>>
+}
+impl ScriptedWindowSource {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new scripted source without any steps.
+	pub fn new() -> ScriptedWindowSource {
+		ScriptedWindowSource::default()
+	}
+
+	/// Return self with a focus step.
+	pub fn with_focus(self, window:ActiveWindowInfo) -> Self {
+		self.focus(window);
+		self
+	}
+
+	/// Return self with a window that is already focused when the system starts running.
+	pub fn with_current_window(self, window:ActiveWindowInfo) -> Self {
+		*self.current_window.lock().unwrap() = Some(window);
+		self
+	}
+
+
+
+	/* USAGE METHODS */
+
+	/// Focus a window.
+	/// If the source was not started yet, the step is forwarded when it starts.
+	/// Unlike other sources, every focus step is handled, even when newer steps were pushed before the system caught up.
+	pub fn focus(&self, window:ActiveWindowInfo) {
+		self.add_step(move |remote| remote.handle_window_change_in_order(&window));
+	}
+
+	/// Close the window with the given id.
+	pub fn close_window(&self, window_id:u64) {
+		self.add_step(move |remote| remote.handle_window_closed(window_id));
+	}
+
+	/// Exit the process with the given id.
+	pub fn exit_process(&self, pid:u32) {
+		self.add_step(move |remote| remote.handle_process_exited(pid));
+	}
+
+	/// Wait until the system has handled all steps pushed so far.
+	/// Returns false if the system did not catch up within the timeout.
+	pub fn flush(&self, timeout:Duration) -> bool {
+		let (sender, receiver) = mpsc::channel();
+		self.add_step(move |remote| remote.execute_on_current_profile(move |_| { let _ = sender.send(()); }));
+		receiver.recv_timeout(timeout).is_ok()
+	}
+
+	/// Forward a step to the system, or keep it until the source is started.
+	fn add_step<Step:FnOnce(&WindowRelativeSystemRemoteControl) + Send + 'static>(&self, step:Step) {
+		match &*self.remote.lock().unwrap() {
+			Some(remote) => step(remote),
+			None => self.pending_steps.lock().unwrap().push(Box::new(step))
+		}
+	}
+}
+impl WindowEventSource for ScriptedWindowSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+		let mut stored_remote:MutexGuard<'_, Option<WindowRelativeSystemRemoteControl>> = self.remote.lock().unwrap();
+		for step in self.pending_steps.lock().unwrap().drain(..) {
+			step(&remote);
+		}
+		*stored_remote = Some(remote);
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		*self.remote.lock().unwrap() = None;
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		self.current_window.lock().unwrap().clone()
+	}
+}
\ No newline at end of file
@@ -0,0 +1,172 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, ScriptedWindowSource, TaskSystem, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, WindowRelativeSystemRemoteControl, implement_window_relative_profile_essentials };
+	use crate::system_u::tests::{ WindowRelativeProfileCore, recording_system, scripted_window };
+	use std::{ error::Error, sync::{ Arc, Mutex, mpsc }, thread, time::Duration };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	const FLUSH_TIMEOUT:Duration = Duration::from_millis(500);
+	fn run_scripted_system(source:&ScriptedWindowSource, log:&Arc<Mutex<Vec<String>>>) {
+		let mut system:WindowRelativeSystem = recording_system(source, log);
+		thread::spawn(move || {
+			system.run();
+		});
+	}
+
+
+
+	#[test]
+	fn test_scripted_focus_runs_handlers_in_order() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		run_scripted_system(&source, &log);
+		source.focus(scripted_window(2, "browser.exe"));
+		source.focus(scripted_window(1, "editor.exe"));
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:open", "editor:activate",
+			"editor:deactivate", "browser:open", "browser:activate",
+			"browser:deactivate", "editor:activate"
+		]);
+	}
+
+	#[test]
+	fn test_scripted_unknown_window_activates_default_profile() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new();
+		run_scripted_system(&source, &log);
+		source.focus(scripted_window(1, "editor.exe"));
+		source.focus(scripted_window(2, "unknown.exe"));
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate", "editor:deactivate", "default:open", "default:activate"]);
+	}
+
+	#[test]
+	fn test_scripted_focus_within_same_profile_does_not_reactivate() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new();
+		run_scripted_system(&source, &log);
+		source.focus(scripted_window(1, "editor.exe"));
+		source.focus(scripted_window(2, "editor.exe"));
+		source.focus(scripted_window(2, "editor.exe"));
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate", "editor:window_changed"]);
+	}
+
+	#[test]
+	fn test_scripted_flush_times_out_without_running_system() {
+		assert!(!ScriptedWindowSource::new().flush(Duration::from_millis(10)));
+	}
+
+	#[test]
+	fn test_scripted_current_window_is_activated_on_startup() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_current_window(scripted_window(1, "browser.exe"));
+		run_scripted_system(&source, &log);
+		source.focus(scripted_window(2, "editor.exe"));
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec!["browser:open", "browser:activate", "browser:deactivate", "editor:open", "editor:activate"]);
+	}
+
+	#[test]
+	fn test_scripted_close_of_startup_window_closes_its_profile() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_current_window(scripted_window(1, "browser.exe"));
+		run_scripted_system(&source, &log);
+		source.close_window(1);
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec!["browser:open", "browser:activate", "browser:deactivate", "browser:close", "default:open", "default:activate"]);
+	}
+
+	#[test]
+	fn test_scripted_startup_activation_can_be_disabled() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_current_window(scripted_window(1, "browser.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log).with_startup_activation(false);
+		thread::spawn(move || {
+			system.run();
+		});
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert!(log.lock().unwrap().is_empty());
+	}
+
+	#[test]
+	fn test_scripted_stop_closes_profiles_and_allows_restart() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe")).with_focus(scripted_window(2, "browser.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log);
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		let (sender, receiver) = mpsc::channel();
+		thread::spawn(move || {
+			system.run();
+			sender.send(()).unwrap();
+			system.run();
+		});
+
+		// Stopping makes run return after deactivating and closing.
+		assert!(source.flush(FLUSH_TIMEOUT));
+		remote.stop();
+		receiver.recv_timeout(FLUSH_TIMEOUT).unwrap();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:open", "editor:activate",
+			"editor:deactivate", "browser:open", "browser:activate",
+			"browser:deactivate", "browser:close", "editor:close"
+		]);
+
+		// Running again re-opens profiles.
+		log.lock().unwrap().clear();
+		source.focus(scripted_window(1, "editor.exe"));
+		assert!(source.flush(FLUSH_TIMEOUT));
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate"]);
+	}
+
+	#[test]
+	fn test_scripted_steps_without_running_thread() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log);
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate"]);
+		source.focus(scripted_window(2, "browser.exe"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec!["editor:open", "editor:activate", "editor:deactivate", "browser:open", "browser:activate"]);
+	}
+
+	#[test]
+	fn test_scripted_handlers_receive_window_snapshot() {
+		struct ClassProfile {
+			name:&'static str,
+			process_name:&'static str,
+			task_system:TaskSystem,
+			status:WindowRelativeProfileStatus,
+			events:Arc<Mutex<Vec<(String, ActiveWindowInfo)>>>
+		}
+		implement_window_relative_profile_essentials!(ClassProfile);
+		impl WindowRelativeProfile for ClassProfile {
+			fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+				active_window.class == "terminal"
+			}
+			fn on_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+				self.events.lock().unwrap().push((event_name.to_string(), window.clone()));
+				Ok(())
+			}
+		}
+
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let events:Arc<Mutex<Vec<(String, ActiveWindowInfo)>>> = Arc::new(Mutex::new(Vec::new()));
+		let terminal:ActiveWindowInfo = ActiveWindowInfo::new(7, "shell", "Terminal").with_pid(70).with_class("terminal").with_executable_path("/usr/bin/shell");
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(terminal.clone());
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new("default", "default.exe").with_log(&log))
+			.with_profile(ClassProfile { name: "class", process_name: "", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), events: events.clone() })
+			.with_event_source(source.clone());
+		system.run_until_idle();
+		source.focus(scripted_window(2, "browser.exe"));
+		system.run_until_idle();
+		assert_eq!(*events.lock().unwrap(), vec![
+			("open".to_string(), terminal.clone()),
+			("activate".to_string(), terminal.clone()),
+			("deactivate".to_string(), terminal)
+		]);
+	}
+}
\ No newline at end of file
@@ -0,0 +1,159 @@
+use crate::{ ActiveWindowInfo, ProcessWatcher, WindowEventSource, WindowGeometry, WindowRelativeSystemRemoteControl, window_info };
+use std::{ env, error::Error, io::{ Read, Write }, net::Shutdown, os::unix::net::UnixStream, path::PathBuf, sync::{ Arc, atomic::{ AtomicBool, Ordering } }, thread };
+use serde_json::Value;
+
+
+
+const IPC_MAGIC:&[u8] = b"i3-ipc";
+const IPC_SUBSCRIBE:u32 = 2;
+const IPC_GET_TREE:u32 = 4;
+const IPC_EVENT_WINDOW:u32 = 0x80000003;
+
+
+
+/// Window event source using the IPC socket of sway or i3.
+/// Connects to the socket in `SWAYSOCK`, or `I3SOCK` if that is not set.
+#[derive(Default)]
+pub struct SwayWindowSource {
+	socket_path:Option<PathBuf>,
+	stream:Option<UnixStream>,
+	running:Arc<AtomicBool>
+}
+impl SwayWindowSource {
+
+	/// Create a new sway/i3 source using the socket from the environment.
+	pub fn new() -> SwayWindowSource {
+		SwayWindowSource::default()
+	}
+
+	/// Return self connecting to a specific socket.
+	pub fn with_socket_path(mut self, socket_path:&str) -> Self {
+		self.socket_path = Some(PathBuf::from(socket_path));
+		self
+	}
+
+	/// Get the path of the socket to connect to.
+	fn socket_path(&self) -> Result<PathBuf, Box<dyn Error>> {
+		match &self.socket_path {
+			Some(path) => Ok(path.clone()),
+			None => env::var("SWAYSOCK").or_else(|_| env::var("I3SOCK")).map(PathBuf::from).map_err(|_| "Neither SWAYSOCK nor I3SOCK is set.".into())
+		}
+	}
+}
+impl WindowEventSource for SwayWindowSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+
+		// Connect and subscribe to window events.
+		let mut stream:UnixStream = UnixStream::connect(self.socket_path()?)?;
+		write_message(&mut stream, IPC_SUBSCRIBE, br#"["window"]"#)?;
+		let (_, reply) = read_message(&mut stream)?;
+		if serde_json::from_slice::<Value>(&reply)?["success"] != Value::Bool(true) {
+			return Err("sway/i3 refused the window event subscription.".into());
+		}
+
+		// Keep listening for focus events, title changes of the focused window and closed windows.
+		// A clone of the stream is kept, so stopping the source can unblock the listener thread by shutting the stream down.
+		self.stream = Some(stream.try_clone()?);
+		self.running = Arc::new(AtomicBool::new(true));
+		let running:Arc<AtomicBool> = self.running.clone();
+		let process_watcher:ProcessWatcher = ProcessWatcher::start(remote.clone(), ProcessWatcher::DEFAULT_INTERVAL);
+		if let Some(window) = self.current_window() {
+			process_watcher.watch(window.pid);
+		}
+		thread::spawn(move || {
+			loop {
+				match read_message(&mut stream) {
+					Ok((IPC_EVENT_WINDOW, payload)) => {
+						let Ok(event) = serde_json::from_slice::<Value>(&payload) else { continue; };
+						if event["change"] == "focus" || (event["change"] == "title" && event["container"]["focused"] == true) {
+							let window:ActiveWindowInfo = window_info_from_container(&event["container"]);
+							process_watcher.watch(window.pid);
+							remote.handle_window_change(&window);
+						} else if event["change"] == "close" {
+							remote.handle_window_closed(event["container"]["id"].as_u64().unwrap_or_default());
+						}
+					},
+					Ok(_) => {},
+					Err(error) => {
+						if running.load(Ordering::SeqCst) {
+							eprintln!("sway/i3 window source lost its connection: {:?}", error);
+						}
+						process_watcher.stop();
+						return;
+					}
+				}
+			}
+		});
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+		if let Some(stream) = self.stream.take() {
+			let _ = stream.shutdown(Shutdown::Both);
+		}
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		let mut stream:UnixStream = UnixStream::connect(self.socket_path().ok()?).ok()?;
+		write_message(&mut stream, IPC_GET_TREE, b"").ok()?;
+		let (_, tree) = read_message(&mut stream).ok()?;
+		find_focused_container(&serde_json::from_slice(&tree).ok()?).map(window_info_from_container)
+	}
+}
+
+
+
+/// Write a message using the i3-ipc framing.
+pub(crate) fn write_message<Stream:Write>(stream:&mut Stream, message_type:u32, payload:&[u8]) -> Result<(), Box<dyn Error>> {
+	let mut message:Vec<u8> = IPC_MAGIC.to_vec();
+	message.extend((payload.len() as u32).to_ne_bytes());
+	message.extend(message_type.to_ne_bytes());
+	message.extend(payload);
+	stream.write_all(&message)?;
+	Ok(())
+}
+
+/// Read a message using the i3-ipc framing.
+/// Returns the message type and payload.
+pub(crate) fn read_message<Stream:Read>(stream:&mut Stream) -> Result<(u32, Vec<u8>), Box<dyn Error>> {
+	let mut header:[u8; 14] = [0; 14];
+	stream.read_exact(&mut header)?;
+	if &header[..6] != IPC_MAGIC {
+		return Err("Received message without i3-ipc magic.".into());
+	}
+	let payload_length:u32 = u32::from_ne_bytes(header[6..10].try_into()?);
+	let message_type:u32 = u32::from_ne_bytes(header[10..14].try_into()?);
+	let mut payload:Vec<u8> = vec![0; payload_length as usize];
+	stream.read_exact(&mut payload)?;
+	Ok((message_type, payload))
+}
+
+/// Find the focused window container in a sway/i3 tree.
+pub(crate) fn find_focused_container(node:&Value) -> Option<&Value> {
+	if node["focused"] == true && (node["type"] == "con" || node["type"] == "floating_con") {
+		return Some(node);
+	}
+	node["nodes"].as_array().into_iter().chain(node["floating_nodes"].as_array()).flatten().find_map(find_focused_container)
+}
+
+/// Create a window info from a sway/i3 container node.
+/// Wayland windows provide an app id, X11 windows provide a window class.
+pub(crate) fn window_info_from_container(container:&Value) -> ActiveWindowInfo {
+	let pid:u32 = container["pid"].as_u64().unwrap_or_default() as u32;
+	let app_id:&str = container["app_id"].as_str().unwrap_or_default();
+	let class:&str = container["window_properties"]["class"].as_str().unwrap_or(app_id);
+	let process_name:String = window_info::process_name_of_pid(pid);
+	let rect:&Value = &container["rect"];
+	ActiveWindowInfo::new(container["id"].as_u64().unwrap_or_default(), &process_name, container["name"].as_str().unwrap_or_default())
+		.with_pid(pid)
+		.with_executable_path(window_info::executable_path_of_pid(pid))
+		.with_class(class)
+		.with_app_id(app_id)
+		.with_geometry(WindowGeometry::new(
+			rect["x"].as_i64().unwrap_or_default() as i32,
+			rect["y"].as_i64().unwrap_or_default() as i32,
+			rect["width"].as_u64().unwrap_or_default() as u32,
+			rect["height"].as_u64().unwrap_or_default() as u32
+		))
+}
\ No newline at end of file
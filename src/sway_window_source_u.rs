@@ -0,0 +1,150 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, SwayWindowSource, TaskSystem, WindowGeometry, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, WindowRelativeSystemRemoteControl, implement_window_relative_profile_essentials, sway_window_source };
+	use std::{ env, fs, io::Cursor, os::unix::net::{ UnixListener, UnixStream }, path::PathBuf, process::{ Child, Command }, sync::mpsc, thread, time::{ Duration, Instant } };
+	use serde_json::json;
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct AppIdProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus
+	}
+	implement_window_relative_profile_essentials!(AppIdProfile);
+	impl WindowRelativeProfile for AppIdProfile {
+		fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+			active_window.app_id == self.name
+		}
+	}
+	impl AppIdProfile {
+		fn new(app_id:&'static str) -> AppIdProfile {
+			AppIdProfile { name: app_id, process_name: "", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default() }
+		}
+	}
+
+
+
+	/// Create a fake sway server that answers tree requests and replays the given events to subscribers.
+	/// Subscriptions are kept open, so sources do not lose their connection after the events were replayed.
+	fn fake_sway_server(name:&str, tree:serde_json::Value, events:Vec<serde_json::Value>) -> PathBuf {
+		let socket_path:PathBuf = env::temp_dir().join(format!("wrs_{}_{}.sock", name, std::process::id()));
+		let _ = fs::remove_file(&socket_path);
+		let listener:UnixListener = UnixListener::bind(&socket_path).unwrap();
+		thread::spawn(move || {
+			let mut subscriptions:Vec<UnixStream> = Vec::new();
+			for mut stream in listener.incoming().flatten() {
+				let (message_type, payload) = sway_window_source::read_message(&mut stream).unwrap();
+				if message_type == 4 {
+					sway_window_source::write_message(&mut stream, 4, tree.to_string().as_bytes()).unwrap();
+					continue;
+				}
+				assert_eq!(message_type, 2);
+				assert_eq!(payload, br#"["window"]"#);
+				sway_window_source::write_message(&mut stream, 2, br#"{"success":true}"#).unwrap();
+				for event in &events {
+					sway_window_source::write_message(&mut stream, 0x80000003, event.to_string().as_bytes()).unwrap();
+				}
+				subscriptions.push(stream);
+			}
+		});
+		socket_path
+	}
+
+	/// Wait until the profile with the given name is active.
+	fn await_active_profile(remote:&WindowRelativeSystemRemoteControl, profile_name:&str) -> bool {
+		let start:Instant = Instant::now();
+		while start.elapsed() < Duration::from_secs(2) {
+			let (sender, receiver) = mpsc::channel();
+			remote.execute_on_current_profile(move |profile| { let _ = sender.send(profile.name().to_string()); });
+			if receiver.recv_timeout(Duration::from_millis(100)).map(|name| name == profile_name).unwrap_or(false) {
+				return true;
+			}
+			thread::sleep(Duration::from_millis(10));
+		}
+		false
+	}
+
+
+
+	#[test]
+	fn test_sway_message_framing() {
+		let mut buffer:Vec<u8> = Vec::new();
+		sway_window_source::write_message(&mut buffer, 2, b"[]").unwrap();
+		assert_eq!(&buffer[..6], b"i3-ipc");
+		assert_eq!(sway_window_source::read_message(&mut Cursor::new(buffer)).unwrap(), (2, b"[]".to_vec()));
+		assert!(sway_window_source::read_message(&mut Cursor::new(b"not-ipc-message".to_vec())).is_err());
+	}
+
+	#[test]
+	fn test_sway_container_mapping() {
+		let wayland_window:ActiveWindowInfo = sway_window_source::window_info_from_container(&json!({ "id": 12, "name": "Terminal", "app_id": "foot", "pid": 0, "rect": { "x": 5, "y": 30, "width": 640, "height": 480 } }));
+		assert_eq!(wayland_window, ActiveWindowInfo::new(12, "", "Terminal").with_class("foot").with_app_id("foot").with_geometry(WindowGeometry::new(5, 30, 640, 480)));
+		let x11_window:ActiveWindowInfo = sway_window_source::window_info_from_container(&json!({ "id": 13, "name": "Browser", "app_id": null, "window_properties": { "class": "Firefox" } }));
+		assert_eq!(x11_window, ActiveWindowInfo::new(13, "", "Browser").with_class("Firefox"));
+	}
+
+	#[test]
+	fn test_sway_focused_container_lookup() {
+		let tree:serde_json::Value = json!({ "type": "root", "nodes": [{ "type": "workspace", "nodes": [
+			{ "type": "con", "id": 1, "focused": false, "app_id": "editor" }
+		], "floating_nodes": [
+			{ "type": "floating_con", "id": 2, "focused": true, "app_id": "foot" }
+		] }] });
+		assert_eq!(sway_window_source::find_focused_container(&tree).unwrap()["id"], 2);
+		assert!(sway_window_source::find_focused_container(&json!({ "type": "root", "focused": true, "nodes": [] })).is_none());
+	}
+
+	#[test]
+	fn test_sway_source_activates_focused_window_on_startup() {
+		let socket_path:PathBuf = fake_sway_server("sway_startup", json!({ "type": "root", "nodes": [{ "type": "con", "id": 1, "focused": true, "app_id": "editor" }] }), Vec::new());
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(AppIdProfile::new("default"))
+			.with_profile(AppIdProfile::new("editor"))
+			.with_event_source(SwayWindowSource::new().with_socket_path(socket_path.to_str().unwrap()));
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		thread::spawn(move || {
+			system.run();
+		});
+		assert!(await_active_profile(&remote, "editor"));
+		let _ = fs::remove_file(socket_path);
+	}
+
+	#[test]
+	fn test_sway_source_closes_profile_when_startup_process_exits() {
+		let mut child:Child = Command::new("sleep").arg("30").spawn().unwrap();
+		let socket_path:PathBuf = fake_sway_server("sway_startup_exit", json!({ "type": "root", "nodes": [{ "type": "con", "id": 1, "focused": true, "app_id": "editor", "pid": child.id() }] }), Vec::new());
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(AppIdProfile::new("default"))
+			.with_profile(AppIdProfile::new("editor"))
+			.with_event_source(SwayWindowSource::new().with_socket_path(socket_path.to_str().unwrap()));
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		thread::spawn(move || {
+			system.run();
+		});
+		assert!(await_active_profile(&remote, "editor"));
+
+		// The child is not reaped, so it stays a zombie process until the end of the test.
+		child.kill().unwrap();
+		assert!(await_active_profile(&remote, "default"));
+		let _ = child.wait();
+		let _ = fs::remove_file(socket_path);
+	}
+
+	#[test]
+	fn test_sway_source_activates_profile_by_app_id() {
+		let socket_path:PathBuf = fake_sway_server("sway_focus", json!({}), vec![
+			json!({ "change": "new", "container": { "id": 1, "name": "Editor", "app_id": "editor" } }),
+			json!({ "change": "focus", "container": { "id": 2, "name": "Terminal", "app_id": "foot" } })
+		]);
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(AppIdProfile::new("default"))
+			.with_profile(AppIdProfile::new("editor"))
+			.with_profile(AppIdProfile::new("foot"))
+			.with_event_source(SwayWindowSource::new().with_socket_path(socket_path.to_str().unwrap()));
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		thread::spawn(move || {
+			system.run();
+		});
+		assert!(await_active_profile(&remote, "foot"));
+		let _ = fs::remove_file(socket_path);
+	}
+}
\ No newline at end of file
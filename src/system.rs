@@ -1,18 +1,155 @@
+use crate::{ ActiveWindowInfo, Clock, EventPropagation, ProfileError, ProfilePhase, handler_guard::{ FaultKey, HandlerGuard }, SystemClock, TransitionContext, TransitionReason, WindowEventSource, WindowMatcher, WindowRelativeProfileStatus, WindowRelativeProfile, window_event_source };
 use modifications_queue::{ModificationsQueue, ModificationsQueueRemote};
-use crate::{ WindowRelativeProfileStatus, WindowRelativeProfile, window_hook };
-use std::{ error::Error, sync::Arc };
-use window_controller::WindowController;
+use std::{ error::Error, mem, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering }, mpsc::{ self, RecvTimeoutError, Sender } }, thread, time::{ Duration, Instant } };
+
+
+
+/// Decides which of the matching non-overlay profiles are activated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ProfileActivationMode {
+
+	/// Only activate the highest-priority matching profile.
+	#[default]
+	FirstMatch,
+
+	/// Activate every matching profile.
+	AllMatches
+}
+
+
+
+/// Counts the window changes sent through remotes.
+/// Every change gets a sequence number, so the system can skip changes that were replaced by a newer change before they were handled.
+#[derive(Default)]
+struct WindowChangeCounters {
+	latest:AtomicUsize,
+	handled:AtomicUsize,
+	coalesced:AtomicUsize
+}
+
+
+
+type InstanceFactory = Box<dyn Fn() -> Box<dyn WindowRelativeProfile> + Send + Sync>;
+
+
+
+/// A profile registered in the system.
+/// Profiles with an instance factory keep a separate instance per window, the registered profile then only serves as template.
+/// Other profiles keep track of the windows they were opened for, so they can be closed once all of them are gone.
+struct RegisteredProfile {
+	added_index:usize,
+	profile:Box<dyn WindowRelativeProfile>,
+	priority:i32,
+	is_overlay:bool,
+	matcher:Option<WindowMatcher>,
+	instance_factory:Option<InstanceFactory>,
+	instances:Vec<(ActiveWindowInfo, Box<dyn WindowRelativeProfile>)>,
+	windows:Vec<ActiveWindowInfo>
+}
+impl RegisteredProfile {
+
+	/// Whether or not the profile matches the given window.
+	/// Uses the matcher stored on registration, profiles that do not declare a matcher do their own matching.
+	fn matches_window(&self, window:&ActiveWindowInfo) -> bool {
+		match &self.matcher {
+			Some(matcher) => matcher.matches(window),
+			None => self.profile.matches_window(window)
+		}
+	}
+
+	/// Get the matcher used to find shadowed profiles.
+	/// Overlay profiles and profiles that do not declare a matcher are never shadowed and never shadow other profiles.
+	fn shadowing_matcher(&self) -> Option<&WindowMatcher> {
+		self.matcher.as_ref().filter(|_| !self.is_overlay)
+	}
+
+	/// Whether or not this profile keeps a separate instance per window.
+	fn is_per_window(&self) -> bool {
+		self.instance_factory.is_some()
+	}
+
+	/// Get the key the failures of the profile handling the window with the given id are counted under.
+	/// Per-window profiles count the failures of each instance separately.
+	fn fault_key(&self, window_id:u64) -> FaultKey {
+		let instance_window_id:Option<u64> = self.instances.iter().any(|(window, _)| window.window_id == window_id).then_some(window_id);
+		FaultKey::new(Some(self.added_index), instance_window_id)
+	}
+
+	/// Get the key the failures of the profile itself are counted under.
+	/// For per-window profiles these are the failures of the template, like failing to match or to create an instance.
+	fn template_fault_key(&self) -> FaultKey {
+		FaultKey::new(Some(self.added_index), None)
+	}
+
+	/// Get the profile handling the window with the given id.
+	/// For per-window profiles this is the instance of the window, or the template if the window has no instance.
+	fn profile_for_window(&self, window_id:u64) -> &dyn WindowRelativeProfile {
+		match self.instances.iter().find(|(window, _)| window.window_id == window_id) {
+			Some((_, instance)) => &**instance,
+			None => &*self.profile
+		}
+	}
+
+	/// Get the profile handling the window with the given id.
+	/// For per-window profiles this is the instance of the window, or the template if the window has no instance.
+	fn profile_for_window_mut(&mut self, window_id:u64) -> &mut dyn WindowRelativeProfile {
+		match self.instances.iter().position(|(window, _)| window.window_id == window_id) {
+			Some(instance_index) => &mut *self.instances[instance_index].1,
+			None => &mut *self.profile
+		}
+	}
+
+	/// Register a window the profile handles and keep the stored window info up to date.
+	/// Per-window profiles make sure the window has an instance, a panic in the instance factory is given to the error handler.
+	/// Returns false if the window has no instance because creating it failed.
+	fn register_window(&mut self, window:&ActiveWindowInfo, handler_guard:&HandlerGuard) -> bool {
+		match &self.instance_factory {
+			Some(instance_factory) => match self.instances.iter_mut().find(|(instance_window, _)| instance_window.window_id == window.window_id) {
+				Some((instance_window, _)) => *instance_window = window.clone(),
+				None => match handler_guard.check(self.template_fault_key(), self.profile.name(), ProfilePhase::Open, window, instance_factory) {
+					Some(instance) => self.instances.push((window.clone(), instance)),
+					None => return false
+				}
+			},
+			None => match self.windows.iter_mut().find(|registered_window| registered_window.window_id == window.window_id) {
+				Some(registered_window) => *registered_window = window.clone(),
+				None => self.windows.push(window.clone())
+			}
+		}
+		true
+	}
+}
+
+
+
+/// A focus change waiting for the activation delays and deactivation grace periods of the profiles it changes.
+struct PendingFocus {
+	window:ActiveWindowInfo,
+	active_profile_indexes:Vec<Option<usize>>,
+	focused_at:Instant,
+	focus_lost_at:Instant
+}
 
 
 
 pub struct WindowRelativeSystem {
-	profiles:Vec<Box<dyn WindowRelativeProfile>>,
+	profiles:Vec<RegisteredProfile>,
 	default_profile:Box<dyn WindowRelativeProfile>,
-	active_profile_index:Option<usize>,
-	error_handler:Arc<dyn Fn(&str, Box<dyn Error>) + Send + Sync + 'static>,
+	activation_mode:ProfileActivationMode,
+	active_profile_indexes:Vec<Option<usize>>,
+	active_window:ActiveWindowInfo,
+	pending_focus:Option<PendingFocus>,
+	focus_timer:Option<Sender<Duration>>,
+	profile_overridden:bool,
+	clock:Arc<dyn Clock>,
+	ignored_windows:Arc<Mutex<Vec<WindowMatcher>>>,
+	window_changes:Arc<WindowChangeCounters>,
+	handler_guard:HandlerGuard,
 
 	modifications_queue:ModificationsQueue<WindowRelativeSystem>,
-	hook_remote_registered:bool
+	event_source:Option<Box<dyn WindowEventSource>>,
+	startup_activation:bool,
+	running:bool
 }
 impl WindowRelativeSystem {
 
@@ -23,14 +160,92 @@ impl WindowRelativeSystem {
 		WindowRelativeSystem {
 			profiles: Vec::new(),
 			default_profile: Box::new(default_profile),
-			active_profile_index: None,
-			error_handler: Arc::new(|profile_name, error| eprintln!("WindowRelativeSystem error on profile {}: {:?}", profile_name, error)),
+			activation_mode: ProfileActivationMode::default(),
+			active_profile_indexes: vec![None],
+			active_window: ActiveWindowInfo::default(),
+			pending_focus: None,
+			focus_timer: None,
+			profile_overridden: false,
+			clock: Arc::new(SystemClock),
+			ignored_windows: Arc::new(Mutex::new(default_ignored_windows())),
+			window_changes: Arc::new(WindowChangeCounters::default()),
+			handler_guard: HandlerGuard::new(Arc::new(|error| eprintln!("WindowRelativeSystem error: {}", error))),
 
 			modifications_queue: ModificationsQueue::new(),
-			hook_remote_registered: false
+			event_source: window_event_source::default_event_source(),
+			startup_activation: true,
+			running: false
 		}
 	}
 
+	/// Return with a custom window event source.
+	/// Replaces the default event source of the platform.
+	pub fn with_event_source<Source:WindowEventSource>(mut self, event_source:Source) -> Self {
+		self.set_event_source(event_source);
+		self
+	}
+
+	/// Set the window event source.
+	/// Only has effect if the system has not been run yet.
+	pub fn set_event_source<Source:WindowEventSource>(&mut self, event_source:Source) {
+		self.event_source = Some(Box::new(event_source));
+	}
+
+	/// Return self with or without startup activation.
+	/// When enabled, running the system immediately activates the profile of the window that is focused at that moment.
+	pub fn with_startup_activation(mut self, startup_activation:bool) -> Self {
+		self.set_startup_activation(startup_activation);
+		self
+	}
+
+	/// Set whether or not running the system immediately activates the profile of the window that is focused at that moment.
+	/// Enabled by default.
+	pub fn set_startup_activation(&mut self, startup_activation:bool) {
+		self.startup_activation = startup_activation;
+	}
+
+	/// Return self with an activation mode.
+	pub fn with_activation_mode(mut self, activation_mode:ProfileActivationMode) -> Self {
+		self.set_activation_mode(activation_mode);
+		self
+	}
+
+	/// Set which of the matching non-overlay profiles are activated.
+	/// Takes effect on the next window change.
+	pub fn set_activation_mode(&mut self, activation_mode:ProfileActivationMode) {
+		self.activation_mode = activation_mode;
+	}
+
+	/// Return self with a custom clock.
+	pub fn with_clock<CustomClock:Clock>(mut self, clock:CustomClock) -> Self {
+		self.set_clock(clock);
+		self
+	}
+
+	/// Set the clock used to time activation delays and deactivation grace periods.
+	/// Allows tests to use a `FakeClock`.
+	pub fn set_clock<CustomClock:Clock>(&mut self, clock:CustomClock) {
+		self.clock = Arc::new(clock);
+	}
+
+	/// Return self ignoring the windows matched by the given matcher.
+	pub fn with_ignored_windows(mut self, matcher:WindowMatcher) -> Self {
+		self.ignore_windows(matcher);
+		self
+	}
+
+	/// Ignore the windows matched by the given matcher.
+	/// Window changes to ignored windows are dropped before reaching the system, so the active profiles stay active while they are focused.
+	/// By default the Windows alt-tab switcher is ignored.
+	pub fn ignore_windows(&mut self, matcher:WindowMatcher) {
+		self.ignored_windows.lock().unwrap().push(matcher);
+	}
+
+	/// Stop ignoring windows, including the windows ignored by default.
+	pub fn clear_ignored_windows(&mut self) {
+		self.ignored_windows.lock().unwrap().clear();
+	}
+
 	/// Return with a custom error-handler.
 	/// The arguments given to the handler are the name of the profile and the error that was thrown
 	pub fn with_error_handler<ErrorHandler:Fn(&str, Box<dyn Error>) + Send + Sync + 'static>(mut self, error_handler:ErrorHandler) -> Self {
@@ -40,8 +255,35 @@ impl WindowRelativeSystem {
 
 	/// Set the error handler.
 	/// The arguments given to the handler are the name of the profile and the error that was thrown.
+	/// Adapts the handler to a profile error handler, use `set_profile_error_handler` to receive the phase and window of the error as well.
 	pub fn set_error_handler<ErrorHandler:Fn(&str, Box<dyn Error>) + Send + Sync + 'static>(&mut self, error_handler:ErrorHandler) {
-		self.error_handler = Arc::new(error_handler);
+		self.set_profile_error_handler(move |error:ProfileError| error_handler(&error.profile_name, error.error));
+	}
+
+	/// Return with a custom profile error handler.
+	pub fn with_profile_error_handler<ErrorHandler:Fn(ProfileError) + Send + Sync + 'static>(mut self, error_handler:ErrorHandler) -> Self {
+		self.set_profile_error_handler(error_handler);
+		self
+	}
+
+	/// Set the profile error handler.
+	/// The handler receives the name of the profile, the phase and event the error occurred in, the window given to the failing handler and the error itself.
+	/// Replaces the error handler set with `set_error_handler`.
+	pub fn set_profile_error_handler<ErrorHandler:Fn(ProfileError) + Send + Sync + 'static>(&mut self, error_handler:ErrorHandler) {
+		self.handler_guard.error_handler = Arc::new(error_handler);
+	}
+
+	/// Return self with a fault threshold.
+	pub fn with_fault_threshold(mut self, fault_threshold:Option<usize>) -> Self {
+		self.set_fault_threshold(fault_threshold);
+		self
+	}
+
+	/// Set the amount of dispatches in a row in which a handler of a profile has to fail before the profile is faulted.
+	/// Faulted profiles are no longer activated and their handlers are no longer run until they are reset using `reset_faulted_profile`.
+	/// Profiles are never faulted when set to None, which is the default.
+	pub fn set_fault_threshold(&mut self, fault_threshold:Option<usize>) {
+		self.handler_guard.fault_threshold = fault_threshold;
 	}
 
 	/// Return self with a profile.
@@ -51,8 +293,50 @@ impl WindowRelativeSystem {
 	}
 
 	/// Add a profile to the system.
+	/// Profiles are checked from high to low priority. Profiles with the same priority are checked in order of name, then in order of adding.
 	pub fn add_profile<Profile:WindowRelativeProfile + 'static>(&mut self, profile:Profile) {
-		self.profiles.push(Box::new(profile));
+		self.register_profile(Box::new(profile), None);
+	}
+
+	/// Return self with a profile factory.
+	pub fn with_profile_factory<Profile:WindowRelativeProfile + 'static, Factory:Fn() -> Profile + Send + Sync + 'static>(mut self, factory:Factory) -> Self {
+		self.add_profile_factory(factory);
+		self
+	}
+
+	/// Add a profile factory to the system.
+	/// The factory creates a separate profile instance for every window the profile matches, opened on the first focus of that window.
+	/// One extra instance is created as template, used for matching, priority and the name of the profile.
+	pub fn add_profile_factory<Profile:WindowRelativeProfile + 'static, Factory:Fn() -> Profile + Send + Sync + 'static>(&mut self, factory:Factory) {
+		let template:Box<dyn WindowRelativeProfile> = Box::new(factory());
+		self.register_profile(template, Some(Box::new(move || Box::new(factory()))));
+	}
+
+	/// Register a profile in order of priority.
+	/// The priority, overlay flag and matcher of the profile are read once here, so matching does not rebuild the matcher for every window.
+	/// A profile that panics while reading them never matches.
+	fn register_profile(&mut self, profile:Box<dyn WindowRelativeProfile>, instance_factory:Option<InstanceFactory>) {
+		let added_index:usize = self.profiles.len();
+		let properties:Option<(i32, bool, Option<WindowMatcher>)> = self.handler_guard.check(FaultKey::new(Some(added_index), None), profile.name(), ProfilePhase::Properties, &ActiveWindowInfo::default(), || (profile.priority(), profile.is_overlay(), profile.matcher()));
+		let (priority, is_overlay, matcher) = properties.unwrap_or_else(|| (0, false, Some(WindowMatcher::any(Vec::new()))));
+		let index:usize = self.profiles.partition_point(|existing| existing.priority > priority || (existing.priority == priority && existing.profile.name() <= profile.name()));
+		self.profiles.insert(index, RegisteredProfile { added_index, profile, priority, is_overlay, matcher, instance_factory, instances: Vec::new(), windows: Vec::new() });
+
+		// Keep pointing to the same active profiles.
+		let pending_profile_indexes:&mut [Option<usize>] = self.pending_focus.as_mut().map(|pending_focus| &mut pending_focus.active_profile_indexes[..]).unwrap_or_default();
+		for active_profile_index in self.active_profile_indexes.iter_mut().chain(pending_profile_indexes).flatten() {
+			if *active_profile_index >= index {
+				*active_profile_index += 1;
+			}
+		}
+
+		// Warn about profiles that can never be activated, once, when the profile causing it is added.
+		let profile_name:&str = self.profiles[index].profile.name();
+		for (shadowed_profile_name, shadowing_profile_name) in self.shadowed_profiles() {
+			if shadowed_profile_name == profile_name || shadowing_profile_name == profile_name {
+				eprintln!("WindowRelativeSystem profile {} is shadowed by higher-priority profile {}", shadowed_profile_name, shadowing_profile_name);
+			}
+		}
 	}
 
 
@@ -62,121 +346,586 @@ impl WindowRelativeSystem {
 	/// Get a remote control to the system.
 	/// Allows triggering events and making changes to the system from somewhere else.
 	pub fn create_remote(&self) -> WindowRelativeSystemRemoteControl {
-		WindowRelativeSystemRemoteControl(self.modifications_queue.create_remote())
+		WindowRelativeSystemRemoteControl(self.modifications_queue.create_remote(), self.ignored_windows.clone(), self.window_changes.clone())
 	}
 
 	/// Run the system.
-	/// Listens to window-change events indefinitely.
+	/// Listens to window-change events until the system is stopped.
 	pub fn run(&mut self) {
+		self.start();
 
-		// Launch window-hook if it does not exist yet.
-		if !self.hook_remote_registered {
-			window_hook::register_remote(self.create_remote());
-			self.hook_remote_registered = true;
+		// Keep running the modifications from the queue until the system is stopped.
+		while self.running {
+			for modification in self.modifications_queue.await_change() {
+				modification(self);
+			}
+		}
+		self.shut_down();
+	}
+
+	/// Process all modifications that are currently queued without waiting for new ones.
+	/// Also applies a pending focus change once it is due.
+	/// Allows driving the system from an existing event loop. Starts the system if it is not running yet.
+	/// Returns the amount of processed modifications.
+	pub fn poll(&mut self) -> usize {
+		self.start();
+		let modifications = self.modifications_queue.changes();
+		let modification_count:usize = modifications.len();
+		for modification in modifications {
+			modification(self);
+		}
+		self.apply_pending_focus();
+		if !self.running {
+			self.shut_down();
 		}
+		modification_count
+	}
 
-		// Keep running the modifications from the queue indefinitely.
+	/// Keep processing modifications until the queue is empty, including modifications queued while processing.
+	/// Returns the amount of processed modifications.
+	pub fn run_until_idle(&mut self) -> usize {
+		let mut modification_count:usize = 0;
 		loop {
-			for modification in self.modifications_queue.await_change() {
-				modification(self);
+			let processed:usize = self.poll();
+			modification_count += processed;
+			if processed == 0 || !self.running {
+				return modification_count;
 			}
 		}
 	}
 
-	/// Set a specific window as active.
-	/// Will activate the according window-relative profile.
-	pub fn set_active_window(&mut self, previous_window:&Option<WindowController>, current_window:&WindowController) {
-		let error_handler:Arc<dyn Fn(&str, Box<dyn Error + 'static>) + Send + Sync> = Arc::clone(&self.error_handler);
-		
-		// Find the active profile index.
-		let active_process_name:String = current_window.process_name().unwrap_or_default();
-		let active_process_title:String = current_window.title();
-		let mut next_active_profile_index:Option<usize> = None;
-		for (profile_index, profile) in self.profiles.iter().enumerate() {
-			if profile.matches_window(current_window, &active_process_name, &active_process_title) {
-				next_active_profile_index = Some(profile_index);
-				break;
+	/// Keep processing modifications until the given duration has passed or the system is stopped.
+	/// Returns the amount of processed modifications.
+	pub fn run_for(&mut self, duration:Duration) -> usize {
+		const IDLE_INTERVAL:Duration = Duration::from_millis(1);
+
+		let end:Instant = Instant::now() + duration;
+		let mut modification_count:usize = 0;
+		self.start();
+		while self.running && Instant::now() < end {
+			let processed:usize = self.poll();
+			modification_count += processed;
+			if processed == 0 {
+				thread::sleep(IDLE_INTERVAL.min(end.saturating_duration_since(Instant::now())));
+			}
+		}
+		modification_count
+	}
+
+	/// Whether or not the system is running.
+	pub fn is_running(&self) -> bool {
+		self.running
+	}
+
+	/// Start the window event source and activate the profile of the focused window.
+	/// Does nothing if the system is already running.
+	fn start(&mut self) {
+		if self.running {
+			return;
+		}
+
+		// Start the window event source.
+		// The currently focused window is fetched before starting, as some sources hand their resources to a listener thread.
+		let remote:WindowRelativeSystemRemoteControl = self.create_remote();
+		let mut startup_window:Option<ActiveWindowInfo> = None;
+		if let Some(event_source) = &mut self.event_source {
+			if self.startup_activation {
+				startup_window = event_source.current_window().filter(|window| !is_window_ignored(&self.ignored_windows, window));
+			}
+			if let Err(error) = event_source.start(remote) {
+				eprintln!("WindowRelativeSystem could not start window event source: {:?}", error);
+			}
+		}
+		self.running = true;
+
+		// Activate the profile of the window that is already focused.
+		if let Some(startup_window) = startup_window {
+			self.transition_to_window(&startup_window, TransitionReason::Startup);
+		}
+	}
+
+	/// Stop the system.
+	/// Makes `run` return after the current batch of modifications, deactivating and closing all profiles and stopping the window event source.
+	pub fn stop(&mut self) {
+		self.running = false;
+	}
+
+	/// Deactivate the active profile, close all opened profiles and stop the window event source.
+	/// Leaves the system in a state where it can be run again.
+	fn shut_down(&mut self) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		let active_window:ActiveWindowInfo = self.active_window.clone();
+
+		// Deactivate the active profiles.
+		self.pending_focus = None;
+		self.focus_timer = None;
+		self.profile_overridden = false;
+		let transition:TransitionContext = TransitionContext::new(TransitionReason::Shutdown, self.active_current_profile_name(), None, active_window.clone(), ActiveWindowInfo::default());
+		for active_profile_index in mem::replace(&mut self.active_profile_indexes, vec![None]) {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(active_profile_index, &active_window);
+			self.deactivate_profile_with_index(active_profile_index, &profile_window, &transition);
+		}
+
+		// Close all opened profiles.
+		// Per-window instances are closed with their own window and discarded.
+		close_profile(&mut *self.default_profile, FaultKey::default(), &active_window, &handler_guard);
+		for registered_profile in &mut self.profiles {
+			if registered_profile.is_per_window() {
+				let added_index:usize = registered_profile.added_index;
+				for (instance_window, mut instance) in registered_profile.instances.drain(..) {
+					let instance_key:FaultKey = FaultKey::new(Some(added_index), Some(instance_window.window_id));
+					close_profile(&mut *instance, instance_key, &instance_window, &handler_guard);
+					handler_guard.forget(instance_key);
+				}
+			} else {
+				registered_profile.windows.clear();
+				let key:FaultKey = registered_profile.template_fault_key();
+				close_profile(&mut *registered_profile.profile, key, &active_window, &handler_guard);
 			}
 		}
-		if next_active_profile_index == self.active_profile_index {
+
+		// Stop the window event source.
+		if let Some(event_source) = &mut self.event_source {
+			event_source.stop();
+		}
+		self.running = false;
+	}
+
+	/// Set a specific window as active.
+	/// Will activate the according window-relative profiles.
+	/// When the change activates profiles with an activation delay or deactivates profiles with a deactivation grace period, it is only applied once the window has kept focus long enough.
+	/// A newer focus change replaces a pending one, so focus that quickly returns never deactivates the active profiles. Title changes of the pending window keep its delay running.
+	pub fn set_active_window(&mut self, current_window:&ActiveWindowInfo) {
+
+		// Keep a manual override while its window only changes its title.
+		if self.profile_overridden && self.active_window.is_same_window(current_window) {
+			self.pending_focus = None;
+			self.transition_to_profiles(current_window, self.active_profile_indexes.clone(), TransitionReason::FocusChange);
 			return;
 		}
 
-		// Handle previous profile deactivation.
-		// This code is a bit messy, but makes sure the events and errors are handled when they occur.
-		if let Some(previous_window) = previous_window {
-			let previous_profile:&mut dyn WindowRelativeProfile = self.profile_with_index_mut(self.active_profile_index);
-			if let Err(error) = previous_profile.on_deactivate() {
-				error_handler(previous_profile.name(), error);
+		let now:Instant = self.clock.now();
+		let focus_lost_at:Instant = self.pending_focus.as_ref().map(|pending_focus| pending_focus.focus_lost_at).unwrap_or(now);
+		let focused_at:Instant = self.pending_focus.as_ref().filter(|pending_focus| pending_focus.window.is_same_window(current_window)).map(|pending_focus| pending_focus.focused_at).unwrap_or(now);
+		let active_profile_indexes:Vec<Option<usize>> = self.matching_profile_indexes(current_window);
+		self.pending_focus = Some(PendingFocus { window: current_window.clone(), active_profile_indexes, focused_at, focus_lost_at });
+		self.apply_pending_focus();
+
+		// Make sure a running system checks again once the change is due.
+		if self.running && let Some(due) = self.pending_focus_due() {
+			let remote:WindowRelativeSystemRemoteControl = self.create_remote();
+			let focus_timer:&Sender<Duration> = self.focus_timer.get_or_insert_with(|| start_focus_timer(remote));
+			let _ = focus_timer.send(due.saturating_duration_since(now));
+		}
+	}
+
+	/// Apply the pending focus change if its delays have passed.
+	/// Called automatically while the system is running, systems driven by hand can call this after time passed.
+	pub fn apply_pending_focus(&mut self) {
+		if self.pending_focus_due().is_some_and(|due| self.clock.now() >= due) && let Some(pending_focus) = self.pending_focus.take() {
+			self.profile_overridden = false;
+			self.transition_to_profiles(&pending_focus.window, pending_focus.active_profile_indexes, TransitionReason::FocusChange);
+		}
+	}
+
+	/// Get the moment the pending focus change is due, or None if there is no pending focus change.
+	/// The activation delay counts from the moment the window was focused, the grace period from the moment the active window lost focus.
+	fn pending_focus_due(&self) -> Option<Instant> {
+		let pending_focus:&PendingFocus = self.pending_focus.as_ref()?;
+		let next_active_profile_indexes:&[Option<usize>] = &pending_focus.active_profile_indexes;
+		let profile_delay = |index:&Option<usize>, delay:fn(&dyn WindowRelativeProfile) -> Duration| {
+			let profile:&dyn WindowRelativeProfile = self.profile_with_index(*index);
+			let key:FaultKey = index.and_then(|index| self.profiles.get(index)).map(|registered_profile| registered_profile.template_fault_key()).unwrap_or_default();
+			self.handler_guard.check(key, profile.name(), ProfilePhase::Properties, &pending_focus.window, || delay(profile)).unwrap_or_default()
+		};
+		let activation_delay:Duration = next_active_profile_indexes.iter().filter(|index| !self.active_profile_indexes.contains(index)).map(|index| profile_delay(index, |profile| profile.activation_delay())).max().unwrap_or_default();
+		let deactivation_grace:Duration = self.active_profile_indexes.iter().filter(|index| !next_active_profile_indexes.contains(index)).map(|index| profile_delay(index, |profile| profile.deactivation_grace())).max().unwrap_or_default();
+		Some((pending_focus.focused_at + activation_delay).max(pending_focus.focus_lost_at + deactivation_grace))
+	}
+
+	/// Set a specific window as active for the given reason.
+	fn transition_to_window(&mut self, current_window:&ActiveWindowInfo, reason:TransitionReason) {
+		let next_active_profile_indexes:Vec<Option<usize>> = self.matching_profile_indexes(current_window);
+		self.transition_to_profiles(current_window, next_active_profile_indexes, reason);
+	}
+
+	/// Set a specific window as active with the given stack of matching profiles for the given reason.
+	fn transition_to_profiles(&mut self, current_window:&ActiveWindowInfo, next_active_profile_indexes:Vec<Option<usize>>, reason:TransitionReason) {
+		let previous_profile_name:Option<String> = self.active_current_profile_name();
+		let previous_window:ActiveWindowInfo = mem::replace(&mut self.active_window, current_window.clone());
+		let previous_active_profile_indexes:Vec<Option<usize>> = mem::replace(&mut self.active_profile_indexes, next_active_profile_indexes.clone());
+
+		// Store the title captures of the current profile on the window.
+		self.active_window.title_captures = self.window_for_profile_with_index(self.current_profile_index(), current_window).title_captures;
+		let transition:TransitionContext = TransitionContext::new(reason, previous_profile_name, Some(self.profile_name_with_index(self.current_profile_index())), previous_window.clone(), self.active_window.clone());
+
+		// Handle deactivation of profiles that no longer match.
+		for profile_index in previous_active_profile_indexes.iter().filter(|profile_index| !next_active_profile_indexes.contains(profile_index)) {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(*profile_index, &previous_window);
+			self.deactivate_profile_with_index(*profile_index, &profile_window, &transition);
+		}
+
+		// Handle activation of new profiles and notify profiles that stay active of the window change.
+		for profile_index in next_active_profile_indexes {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(profile_index, current_window);
+			if previous_active_profile_indexes.contains(&profile_index) {
+				let previous_profile_window:ActiveWindowInfo = self.window_for_profile_with_index(profile_index, &previous_window);
+				self.change_window_of_profile_with_index(profile_index, &previous_profile_window, &profile_window, &transition);
+			} else {
+				self.activate_profile_with_index(profile_index, &profile_window, &transition);
 			}
-			if let Err(error) = previous_profile.on_event(previous_window, "deactivate") {
-				error_handler(previous_profile.name(), error);
+		}
+	}
+
+	/// Make the profile with the given name the current profile, regardless of the focused window.
+	/// Active overlay profiles stay active. The override lasts until another window is focused, title changes of the focused window keep it.
+	/// Does nothing if the profile does not exist.
+	pub fn override_current_profile(&mut self, name:&str) {
+		let override_index:Option<usize> = if name == self.default_profile.name() {
+			None
+		} else {
+			match self.profiles.iter().position(|registered_profile| registered_profile.profile.name() == name) {
+				Some(profile_index) => Some(profile_index),
+				None => return
 			}
-			//previous_profile.task_system_mut().stop();
-			*previous_profile.status_mut() = WindowRelativeProfileStatus::Deactivated;
+		};
+		let active_window:ActiveWindowInfo = self.active_window.clone();
+		let transition:TransitionContext = TransitionContext::new(TransitionReason::ManualOverride, self.active_current_profile_name(), Some(name.to_string()), active_window.clone(), active_window.clone());
+
+		// Replace the non-overlay profiles in the stack, keeping it in order of priority.
+		let previous_active_profile_indexes:Vec<Option<usize>> = self.active_profile_indexes.clone();
+		let mut next_active_profile_indexes:Vec<Option<usize>> = previous_active_profile_indexes.iter().copied().filter(|index| *index != override_index && index.is_some_and(|index| self.profiles[index].is_overlay)).collect();
+		next_active_profile_indexes.push(override_index);
+		next_active_profile_indexes.sort_by_key(|index| index.unwrap_or(usize::MAX));
+		self.active_profile_indexes = next_active_profile_indexes.clone();
+		self.active_window.title_captures = self.window_for_profile_with_index(override_index, &active_window).title_captures;
+		self.profile_overridden = true;
+
+		// Deactivate the replaced profiles and activate the override.
+		for profile_index in previous_active_profile_indexes.iter().filter(|profile_index| !next_active_profile_indexes.contains(profile_index)) {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(*profile_index, &active_window);
+			self.deactivate_profile_with_index(*profile_index, &profile_window, &transition);
 		}
+		if !previous_active_profile_indexes.contains(&override_index) {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(override_index, &active_window);
+			self.activate_profile_with_index(override_index, &profile_window, &transition);
+		}
+	}
 
-		// Handle switch to new profile.
-		self.active_profile_index = next_active_profile_index;
+	/// Reset a faulted profile, allowing it to be activated again.
+	/// The profile is opened again on its next activation, which happens on the next matching focus change.
+	pub fn reset_faulted_profile(&mut self, name:&str) {
+		let reset_status = |profile:&mut dyn WindowRelativeProfile| {
+			if profile.status() == &WindowRelativeProfileStatus::Faulted {
+				*profile.status_mut() = WindowRelativeProfileStatus::Uninitialized;
+			}
+		};
+		for registered_profile in self.profiles.iter_mut().filter(|registered_profile| registered_profile.profile.name() == name) {
+			self.handler_guard.reset(Some(registered_profile.added_index));
+			reset_status(&mut *registered_profile.profile);
+			for (_, instance) in &mut registered_profile.instances {
+				reset_status(&mut **instance);
+			}
+		}
+		if self.default_profile.name() == name {
+			self.handler_guard.reset(None);
+			reset_status(&mut *self.default_profile);
+		}
+	}
+
+	/// Notify a profile that stays active that the focused window or its title changed.
+	/// Also re-delivers title captures when they changed.
+	/// Per-window profiles switch instances instead when another window receives focus.
+	fn change_window_of_profile_with_index(&mut self, index:Option<usize>, previous_window:&ActiveWindowInfo, window:&ActiveWindowInfo, transition:&TransitionContext) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		if let Some(registered_profile) = index.and_then(|index| self.profiles.get_mut(index)) {
+			if registered_profile.is_per_window() && previous_window.window_id != window.window_id {
+				self.deactivate_profile_with_index(index, previous_window, transition);
+				self.activate_profile_with_index(index, window, transition);
+				return;
+			}
+			registered_profile.register_window(window, &handler_guard);
+		}
+
+		let key:FaultKey = self.fault_key(index, window.window_id);
+		let profile:&mut dyn WindowRelativeProfile = self.profile_for_window_mut(index, window.window_id);
+		handler_guard.dispatch(key, profile, window, |dispatch| {
+			if !previous_window.is_same_window(window) {
+				dispatch.run(ProfilePhase::WindowChanged, None, |profile| profile.on_window_changed(previous_window, window));
+				dispatch.run(ProfilePhase::WindowChanged, Some("window_changed"), |profile| profile.on_event(window, "window_changed"));
+			} else if previous_window.title != window.title {
+				dispatch.run(ProfilePhase::TitleChanged, None, |profile| profile.on_title_changed(previous_window, window));
+				dispatch.run(ProfilePhase::TitleChanged, Some("title_changed"), |profile| profile.on_event(window, "title_changed"));
+			}
+			if previous_window.title_captures != window.title_captures {
+				dispatch.run(ProfilePhase::TitleChanged, Some("title_captures_changed"), |profile| profile.on_title_captures_changed(window));
+			}
+		});
+	}
 
-		// Handle new profile activation.
-		// This code is a bit messy, but makes sure the events and errors are handled when they occur.
-		let new_profile:&mut dyn WindowRelativeProfile = self.profile_with_index_mut(self.active_profile_index);
-		if new_profile.status() == &WindowRelativeProfileStatus::Uninitialized {
-			if let Err(error) = new_profile.on_open() {
-				error_handler(new_profile.name(), error);
+	/// Get the indexes of the profiles that should be active for the given window, in order of priority.
+	/// The default profile is represented by None and is only included if no non-overlay profile matches.
+	/// Faulted profiles and profiles that panic while matching are skipped, profiles faulted by matching get the `Faulted` status.
+	fn matching_profile_indexes(&mut self, window:&ActiveWindowInfo) -> Vec<Option<usize>> {
+		let mut matching_profile_indexes:Vec<Option<usize>> = Vec::new();
+		let mut failed_profile_indexes:Vec<usize> = Vec::new();
+		let mut found_window_profile:bool = false;
+		for (profile_index, registered_profile) in self.profiles.iter().enumerate() {
+			if self.handler_guard.is_faulted(registered_profile.template_fault_key()) {
+				continue;
 			}
-			if let Err(error) = new_profile.on_event(current_window, "open") {
-				error_handler(new_profile.name(), error);
+			let mut matches_window = || match self.handler_guard.check(registered_profile.template_fault_key(), registered_profile.profile.name(), ProfilePhase::Match, window, || registered_profile.matches_window(window)) {
+				Some(matches) => matches,
+				None => {
+					failed_profile_indexes.push(profile_index);
+					false
+				}
+			};
+			if registered_profile.is_overlay {
+				if matches_window() {
+					matching_profile_indexes.push(Some(profile_index));
+				}
+			} else if (!found_window_profile || self.activation_mode == ProfileActivationMode::AllMatches) && matches_window() {
+				matching_profile_indexes.push(Some(profile_index));
+				found_window_profile = true;
 			}
 		}
-		*new_profile.status_mut() = WindowRelativeProfileStatus::Active;
-		if let Err(error) = new_profile.on_activate() {
-			error_handler(new_profile.name(), error);
+		if !found_window_profile {
+			matching_profile_indexes.push(None);
+		}
+		for profile_index in failed_profile_indexes {
+			let key:FaultKey = self.profiles[profile_index].template_fault_key();
+			if self.handler_guard.is_faulted(key) {
+				self.handler_guard.mark_faulted(key, &mut *self.profiles[profile_index].profile, window);
+			}
+		}
+		matching_profile_indexes
+	}
+
+	/// Get a copy of the given window with the title captures of the profile with the given index.
+	fn window_for_profile_with_index(&self, index:Option<usize>, window:&ActiveWindowInfo) -> ActiveWindowInfo {
+		let mut profile_window:ActiveWindowInfo = window.clone();
+		profile_window.title_captures = match index.and_then(|index| self.profiles.get(index)) {
+			Some(RegisteredProfile { matcher: Some(matcher), .. }) => matcher.title_captures(window),
+			_ => Default::default()
+		};
+		profile_window
+	}
+
+	/// Get the index of the current profile, the highest-priority active profile that is not an overlay.
+	/// Returns None for the default profile.
+	fn current_profile_index(&self) -> Option<usize> {
+		self.active_profile_indexes.iter().copied().find(|index| index.is_none_or(|index| !self.profiles[index].is_overlay)).flatten()
+	}
+
+	/// Get the name of the current profile, or None if the current profile is not active.
+	fn active_current_profile_name(&self) -> Option<String> {
+		let profile:&dyn WindowRelativeProfile = match self.current_profile_index().and_then(|index| self.profiles.get(index)) {
+			Some(registered_profile) => registered_profile.profile_for_window(self.active_window.window_id),
+			None => &*self.default_profile
+		};
+		if profile.status() == &WindowRelativeProfileStatus::Active { Some(profile.name().to_string()) } else { None }
+	}
+
+	/// Get the name of the profile with the given index.
+	/// Will return the name of the default profile on None.
+	fn profile_name_with_index(&self, index:Option<usize>) -> String {
+		match index.and_then(|index| self.profiles.get(index)) {
+			Some(registered_profile) => registered_profile.profile.name().to_string(),
+			None => self.default_profile.name().to_string()
 		}
-		if let Err(error) = new_profile.on_event(current_window, "activate") {
-			error_handler(new_profile.name(), error);
+	}
+
+	/// Activate the profile with the given index, opening it first if required.
+	/// Will activate the default profile on None.
+	/// Per-window profiles activate the instance of the window, creating it if required.
+	fn activate_profile_with_index(&mut self, index:Option<usize>, window:&ActiveWindowInfo, transition:&TransitionContext) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		if let Some(registered_profile) = index.and_then(|index| self.profiles.get_mut(index)) && !registered_profile.register_window(window, &handler_guard) {
+			return;
+		}
+
+		let key:FaultKey = self.fault_key(index, window.window_id);
+		let profile:&mut dyn WindowRelativeProfile = self.profile_for_window_mut(index, window.window_id);
+		handler_guard.dispatch(key, profile, window, |dispatch| {
+			dispatch.run(ProfilePhase::Transition, None, |profile| profile.on_transition(transition));
+			if dispatch.profile().status() == &WindowRelativeProfileStatus::Uninitialized {
+				dispatch.run(ProfilePhase::Open, None, |profile| profile.on_open(window));
+				dispatch.run(ProfilePhase::Open, Some("open"), |profile| profile.on_event(window, "open"));
+			}
+			*dispatch.profile().status_mut() = WindowRelativeProfileStatus::Active;
+			dispatch.run(ProfilePhase::Activate, None, |profile| profile.on_activate(window));
+			dispatch.run(ProfilePhase::Activate, Some("activate"), |profile| profile.on_transition_event(window, "activate", transition));
+			dispatch.run(ProfilePhase::Activate, None, |profile| { apply_status_to_tasks(profile); Ok(()) });
+		});
+	}
+
+	/// Deactivate the profile with the given index if it is active.
+	/// Will deactivate the default profile on None.
+	/// Per-window profiles deactivate the instance of the window.
+	fn deactivate_profile_with_index(&mut self, index:Option<usize>, window:&ActiveWindowInfo, transition:&TransitionContext) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		let key:FaultKey = self.fault_key(index, window.window_id);
+		deactivate_profile(self.profile_for_window_mut(index, window.window_id), key, window, transition, &handler_guard);
+	}
+
+	/// Get the matchers of the ignored windows.
+	pub fn ignored_windows(&self) -> Vec<WindowMatcher> {
+		self.ignored_windows.lock().unwrap().clone()
+	}
+
+	/// Whether or not the given window is ignored.
+	pub fn is_window_ignored(&self, window:&ActiveWindowInfo) -> bool {
+		is_window_ignored(&self.ignored_windows, window)
+	}
+
+	/// Get the amount of window changes received through remotes that were handled.
+	pub fn handled_window_changes(&self) -> usize {
+		self.window_changes.handled.load(Ordering::SeqCst)
+	}
+
+	/// Get the amount of window changes received through remotes that were skipped, because a newer window change was queued before they could be handled.
+	pub fn coalesced_window_changes(&self) -> usize {
+		self.window_changes.coalesced.load(Ordering::SeqCst)
+	}
+
+	/// Get the info of the window that was last set as active.
+	pub fn active_window(&self) -> &ActiveWindowInfo {
+		&self.active_window
+	}
+
+	/// Describe the matcher of each profile in the order they are checked, one profile per line.
+	/// Profiles that do not declare a matcher are shown as custom.
+	pub fn describe_matchers(&self) -> String {
+		let mut lines:Vec<String> = self.profiles.iter().map(|registered_profile| match &registered_profile.matcher {
+			Some(matcher) => format!("{}: {}", registered_profile.profile.name(), matcher),
+			None => format!("{}: (custom)", registered_profile.profile.name())
+		}).collect();
+		lines.push(format!("{}: (default)", self.default_profile.name()));
+		lines.join("\n")
+	}
+
+	/// Get the names of profiles that can never match because a profile that is checked earlier matches all of their windows.
+	/// Returns pairs of the shadowed profile name and the name of the profile shadowing it.
+	/// Based on the declared matchers, detection is conservative and only finds obvious cases.
+	/// Overlay profiles never shadow or get shadowed, neither do profiles when all matching profiles are activated or profiles that do not declare a matcher.
+	/// A warning is printed once for every pair, when the later of both profiles is added.
+	pub fn shadowed_profiles(&self) -> Vec<(String, String)> {
+		let mut shadowed_profiles:Vec<(String, String)> = Vec::new();
+		if self.activation_mode == ProfileActivationMode::AllMatches {
+			return shadowed_profiles;
+		}
+		for (profile_index, registered_profile) in self.profiles.iter().enumerate() {
+			let Some(matcher) = registered_profile.shadowing_matcher() else { continue };
+			if let Some(shadowing_profile) = self.profiles[..profile_index].iter().find(|shadowing_profile| shadowing_profile.shadowing_matcher().is_some_and(|shadowing_matcher| shadowing_matcher.covers(matcher))) {
+				shadowed_profiles.push((registered_profile.profile.name().to_string(), shadowing_profile.profile.name().to_string()));
+			}
+		}
+		shadowed_profiles
+	}
+
+	/// Get the windows that currently have an instance of the per-window profile with the given name.
+	/// Returns an empty list if the profile does not exist or is not a per-window profile.
+	pub fn window_instances(&self, name:&str) -> Vec<ActiveWindowInfo> {
+		self.profiles.iter()
+			.filter(|registered_profile| registered_profile.profile.name() == name)
+			.flat_map(|registered_profile| registered_profile.instances.iter().map(|(window, _)| window.clone()))
+			.collect()
+	}
+
+	/// Get the window-relative profile with the given index.
+	/// Will return the default profile on None. Returns the template for per-window profiles.
+	fn profile_with_index(&self, index:Option<usize>) -> &dyn WindowRelativeProfile {
+		match index.and_then(|index| self.profiles.get(index)) {
+			Some(registered_profile) => &*registered_profile.profile,
+			None => &*self.default_profile
 		}
-		//new_profile.task_system_mut().start();
 	}
 
 	/// Get a mutable window-relative profile with the given index.
-	/// Will return the default profile on None.
+	/// Will return the default profile on None. Returns the instance of the active window for per-window profiles.
 	fn profile_with_index_mut(&mut self, index:Option<usize>) -> &mut dyn WindowRelativeProfile {
-		if let Some(index) = index {
-			if index < self.profiles.len() {
-				return &mut *self.profiles[index];
-			}
+		self.profile_for_window_mut(index, self.active_window.window_id)
+	}
+
+	/// Get a mutable window-relative profile with the given index.
+	/// Will return the default profile on None. Returns the instance of the given window for per-window profiles.
+	fn profile_for_window_mut(&mut self, index:Option<usize>, window_id:u64) -> &mut dyn WindowRelativeProfile {
+		match index.and_then(|index| self.profiles.get_mut(index)) {
+			Some(registered_profile) => registered_profile.profile_for_window_mut(window_id),
+			None => &mut *self.default_profile
+		}
+	}
+
+	/// Get the key the failures of the profile with the given index are counted under.
+	/// Uses the default profile on None. Uses the instance of the given window for per-window profiles.
+	fn fault_key(&self, index:Option<usize>, window_id:u64) -> FaultKey {
+		match index.and_then(|index| self.profiles.get(index)) {
+			Some(registered_profile) => registered_profile.fault_key(window_id),
+			None => FaultKey::default()
 		}
-		&mut *self.default_profile
 	}
 
 
 
 	/* EXECUTION METHODS */
 
-	/// Execute an event on the active profile.
+	/// Execute an event on the stack of active profiles, in order of priority.
+	/// Stops when a profile stops the propagation of the event.
 	pub fn trigger_event(&mut self, event_name:&str) {
-		let error_handler:Arc<dyn Fn(&str, Box<dyn Error + 'static>) + Send + Sync> = Arc::clone(&self.error_handler);
-		let current_profile:&mut (dyn WindowRelativeProfile + 'static) = self.profile_with_index_mut(self.active_profile_index);
-		if let Err(error) = current_profile.execute_event(&WindowController::active(), event_name) {
-			error_handler(current_profile.name(), error);
+		self.trigger_event_in_phase(event_name, ProfilePhase::Event);
+	}
+
+	/// Execute an event on the stack of active profiles, reporting errors in the given phase.
+	fn trigger_event_in_phase(&mut self, event_name:&str, phase:ProfilePhase) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		let active_window:ActiveWindowInfo = self.active_window.clone();
+		for profile_index in self.active_profile_indexes.clone() {
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(profile_index, &active_window);
+			let key:FaultKey = self.fault_key(profile_index, active_window.window_id);
+			let profile:&mut (dyn WindowRelativeProfile + 'static) = self.profile_with_index_mut(profile_index);
+			if handler_guard.dispatch(key, profile, &profile_window, |dispatch| dispatch.run(phase, Some(event_name), |profile| profile.execute_layered_event(&profile_window, event_name))).flatten() == Some(EventPropagation::Stop) {
+				return;
+			}
 		}
 	}
 
 	/// Execute an action on all profiles.
-	/// Includes the default profile.
+	/// Includes the default profile. Per-window profiles are represented by their live instances.
 	pub fn execute_on_all_profiles<Action:Fn(&mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, action:Action) -> Vec<ReturnType> {
-		vec![
-			vec![action(&mut *self.default_profile)],
-			self.profiles.iter_mut().map(|profile| action(&mut **profile)).collect()
-		].into_iter().flatten().collect()
+		self.execute_keyed_on_all_profiles(|_, profile| action(profile))
+	}
+
+	/// Execute an action on all profiles, giving the action the key the failures of each profile are counted under.
+	fn execute_keyed_on_all_profiles<Action:Fn(FaultKey, &mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, action:Action) -> Vec<ReturnType> {
+		let mut results:Vec<ReturnType> = vec![action(FaultKey::default(), &mut *self.default_profile)];
+		for registered_profile in &mut self.profiles {
+			if registered_profile.is_per_window() {
+				let added_index:usize = registered_profile.added_index;
+				results.extend(registered_profile.instances.iter_mut().map(|(window, instance)| action(FaultKey::new(Some(added_index), Some(window.window_id)), &mut **instance)));
+			} else {
+				results.push(action(registered_profile.template_fault_key(), &mut *registered_profile.profile));
+			}
+		}
+		results
+	}
+
+	/// Execute an action on all active profiles, in order of priority.
+	/// Includes overlay profiles.
+	pub fn execute_on_active_profiles<Action:Fn(&mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, action:Action) -> Vec<ReturnType> {
+		self.execute_keyed_on_active_profiles(|_, profile| action(profile))
+	}
+
+	/// Execute an action on all active profiles, giving the action the key the failures of each profile are counted under.
+	fn execute_keyed_on_active_profiles<Action:Fn(FaultKey, &mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, action:Action) -> Vec<ReturnType> {
+		let active_window_id:u64 = self.active_window.window_id;
+		self.active_profile_indexes.clone().into_iter().map(|profile_index| action(self.fault_key(profile_index, active_window_id), self.profile_with_index_mut(profile_index))).collect()
 	}
 
 	/// Execute an action on the currently activated profile.
-	/// Uses the default profile if no profile is active.
+	/// Uses the default profile if no profile is active. Never uses overlay profiles.
 	pub fn execute_on_current_profile<Action:FnOnce(&mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, action:Action) -> ReturnType {
-		action(self.profile_with_index_mut(self.active_profile_index))
+		action(self.profile_with_index_mut(self.current_profile_index()))
 	}
 
 	/// Execute an action on the default profile.
@@ -186,39 +935,307 @@ impl WindowRelativeSystem {
 
 	/// Execute an action on the profile with the given name.
 	/// Does nothing if the profile does not exist.
+	/// Per-window profiles use the instance of the active window, or the template if the active window has no instance.
 	pub fn execute_on_profile_with_name<Action:FnOnce(&mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, name:&str, action:Action) -> Option<ReturnType> {
+		self.execute_keyed_on_profile_with_name(name, |_, profile| action(profile))
+	}
+
+	/// Execute an action on the profile with the given name, giving the action the key the failures of the profile are counted under.
+	fn execute_keyed_on_profile_with_name<Action:FnOnce(FaultKey, &mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, name:&str, action:Action) -> Option<ReturnType> {
 		if name == self.default_profile.name() {
-			return Some(action(&mut *self.default_profile));
+			return Some(action(FaultKey::default(), &mut *self.default_profile));
 		}
-		for profile in &mut self.profiles {
-			if profile.name() == name {
-				return Some(action(&mut **profile));
+		let active_window_id:u64 = self.active_window.window_id;
+		for registered_profile in &mut self.profiles {
+			if registered_profile.profile.name() == name {
+				return Some(action(registered_profile.fault_key(active_window_id), registered_profile.profile_for_window_mut(active_window_id)));
 			}
 		}
 		None
 	}
+
+	/// Execute an action on all live instances of the per-window profile with the given name.
+	/// Does nothing if the profile does not exist or is not a per-window profile.
+	pub fn execute_on_window_instances<Action:Fn(&mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, name:&str, action:Action) -> Vec<ReturnType> {
+		self.execute_keyed_on_window_instances(name, |_, instance| action(instance))
+	}
+
+	/// Execute an action on all live instances of the per-window profile with the given name, giving the action the key the failures of each instance are counted under.
+	fn execute_keyed_on_window_instances<Action:Fn(FaultKey, &mut dyn WindowRelativeProfile) -> ReturnType, ReturnType>(&mut self, name:&str, action:Action) -> Vec<ReturnType> {
+		self.profiles.iter_mut()
+			.filter(|registered_profile| registered_profile.profile.name() == name)
+			.flat_map(|registered_profile| {
+				let added_index:usize = registered_profile.added_index;
+				registered_profile.instances.iter_mut().map(move |(window, instance)| (FaultKey::new(Some(added_index), Some(window.window_id)), instance))
+			})
+			.map(|(key, instance)| action(key, &mut **instance))
+			.collect()
+	}
+
+	/// Handle the window with the given id being closed.
+	/// Closes the per-window profile instances of the window and profiles of which all windows are closed.
+	pub fn close_window(&mut self, window_id:u64) {
+		self.close_windows(|window| window.window_id == window_id);
+	}
+
+	/// Handle the process with the given id exiting.
+	/// Closes the per-window profile instances of its windows and profiles of which all windows are closed.
+	pub fn close_process(&mut self, pid:u32) {
+		if pid != 0 {
+			self.close_windows(|window| window.pid == pid);
+		}
+	}
+
+	/// Close the per-window profile instances of the windows matching the given filter and profiles of which all windows match it.
+	/// Deactivates the profiles first if they are active. Closed profiles are opened again on their next activation.
+	/// Falls back to the default profile when the current profile or its active instance was closed.
+	fn close_windows<Filter:Fn(&ActiveWindowInfo) -> bool>(&mut self, is_closed:Filter) {
+		let handler_guard:HandlerGuard = self.handler_guard.clone();
+		let previous_profile_name:Option<String> = self.active_current_profile_name();
+
+		// Find the closed instances and profiles.
+		// Profiles of which the active instance is closed are no longer active, just like closed profiles.
+		let mut closed_instances:Vec<(FaultKey, ActiveWindowInfo, Box<dyn WindowRelativeProfile>)> = Vec::new();
+		let mut closed_profiles:Vec<(usize, ActiveWindowInfo)> = Vec::new();
+		let mut closed_active_indexes:Vec<usize> = Vec::new();
+		for (profile_index, registered_profile) in self.profiles.iter_mut().enumerate() {
+			if registered_profile.is_per_window() {
+				let (closed_profile_instances, open_instances):(Vec<_>, Vec<_>) = mem::take(&mut registered_profile.instances).into_iter().partition(|(window, _)| is_closed(window));
+				registered_profile.instances = open_instances;
+				if closed_profile_instances.iter().any(|(_, instance)| instance.status() == &WindowRelativeProfileStatus::Active) {
+					closed_active_indexes.push(profile_index);
+				}
+				let added_index:usize = registered_profile.added_index;
+				closed_instances.extend(closed_profile_instances.into_iter().map(|(window, instance)| (FaultKey::new(Some(added_index), Some(window.window_id)), window, instance)));
+			} else {
+				let (closed_windows, open_windows):(Vec<ActiveWindowInfo>, Vec<ActiveWindowInfo>) = mem::take(&mut registered_profile.windows).into_iter().partition(|window| is_closed(window));
+				registered_profile.windows = open_windows;
+				if let Some(last_window) = closed_windows.last() && registered_profile.windows.is_empty() {
+					closed_profiles.push((profile_index, last_window.clone()));
+					closed_active_indexes.push(profile_index);
+				}
+			}
+		}
+
+		// Closed profiles are no longer active, fall back to the default profile if no window profile is left.
+		let mut activate_default_profile:bool = false;
+		if !closed_active_indexes.is_empty() {
+			self.active_profile_indexes.retain(|index| index.is_none_or(|index| !closed_active_indexes.contains(&index)));
+			if !self.active_profile_indexes.iter().any(|index| index.is_none_or(|index| !self.profiles[index].is_overlay)) {
+				self.active_profile_indexes.push(None);
+				activate_default_profile = true;
+			}
+		}
+
+		// Deactivate and close the closed instances and profiles.
+		let next_profile_name:Option<String> = if activate_default_profile { Some(self.default_profile.name().to_string()) } else { self.active_current_profile_name() };
+		let active_window:ActiveWindowInfo = self.active_window.clone();
+		for (key, window, mut instance) in closed_instances {
+			let transition:TransitionContext = TransitionContext::new(TransitionReason::WindowClosed, previous_profile_name.clone(), next_profile_name.clone(), window.clone(), active_window.clone());
+			deactivate_profile(&mut *instance, key, &window, &transition, &handler_guard);
+			close_profile(&mut *instance, key, &window, &handler_guard);
+			handler_guard.forget(key);
+		}
+		for (profile_index, window) in closed_profiles {
+			let transition:TransitionContext = TransitionContext::new(TransitionReason::WindowClosed, previous_profile_name.clone(), next_profile_name.clone(), window.clone(), active_window.clone());
+			let key:FaultKey = self.profiles[profile_index].template_fault_key();
+			let profile:&mut dyn WindowRelativeProfile = &mut *self.profiles[profile_index].profile;
+			deactivate_profile(profile, key, &window, &transition, &handler_guard);
+			close_profile(profile, key, &window, &handler_guard);
+		}
+
+		// Activate the fallback.
+		if activate_default_profile {
+			self.profile_overridden = false;
+			let transition:TransitionContext = TransitionContext::new(TransitionReason::WindowClosed, previous_profile_name, next_profile_name, active_window.clone(), active_window.clone());
+			let profile_window:ActiveWindowInfo = self.window_for_profile_with_index(None, &active_window);
+			self.activate_profile_with_index(None, &profile_window, &transition);
+		}
+	}
+}
+
+
+
+/// Deactivate a profile if it is active.
+fn deactivate_profile(profile:&mut dyn WindowRelativeProfile, key:FaultKey, window:&ActiveWindowInfo, transition:&TransitionContext, handler_guard:&HandlerGuard) {
+	if profile.status() == &WindowRelativeProfileStatus::Active {
+		handler_guard.dispatch(key, profile, window, |dispatch| {
+			dispatch.run(ProfilePhase::Transition, None, |profile| profile.on_transition(transition));
+			dispatch.run(ProfilePhase::Deactivate, None, |profile| profile.on_deactivate(window));
+			dispatch.run(ProfilePhase::Deactivate, Some("deactivate"), |profile| profile.on_transition_event(window, "deactivate", transition));
+			*dispatch.profile().status_mut() = WindowRelativeProfileStatus::Deactivated;
+			dispatch.run(ProfilePhase::Deactivate, None, |profile| { apply_status_to_tasks(profile); Ok(()) });
+		});
+	}
+}
+
+/// Close a profile if it was opened.
+/// Faulted profiles stay faulted.
+fn close_profile(profile:&mut dyn WindowRelativeProfile, key:FaultKey, window:&ActiveWindowInfo, handler_guard:&HandlerGuard) {
+	if profile.status() != &WindowRelativeProfileStatus::Uninitialized && profile.status() != &WindowRelativeProfileStatus::Faulted {
+		handler_guard.dispatch(key, profile, window, |dispatch| {
+			dispatch.run(ProfilePhase::Close, None, |profile| profile.on_close(window));
+			dispatch.run(ProfilePhase::Close, Some("close"), |profile| profile.on_event(window, "close"));
+			*dispatch.profile().status_mut() = WindowRelativeProfileStatus::Uninitialized;
+			dispatch.run(ProfilePhase::Close, None, |profile| { apply_status_to_tasks(profile); Ok(()) });
+		});
+	}
+}
+
+/// Start or stop the task system and tasks of a profile according to its status.
+/// The task system follows the task policy of the profile, tasks follow their own policy.
+pub(crate) fn apply_status_to_tasks(profile:&mut dyn WindowRelativeProfile) {
+	let running:bool = profile.task_policy().runs_with_status(profile.status());
+	profile.set_task_system_running(running);
+	for task in profile.tasks() {
+		task.apply_profile_status(profile.status());
+	}
+}
+
+
+/// Start the thread that lets a running system apply its pending focus change once it is due.
+/// Each wait sent to the timer replaces the previous one, as a newer focus change replaces the pending one. The thread exits once the sender is dropped.
+fn start_focus_timer(remote:WindowRelativeSystemRemoteControl) -> Sender<Duration> {
+	let (sender, waits) = mpsc::channel::<Duration>();
+	thread::spawn(move || {
+		let mut due:Option<Instant> = None;
+		loop {
+			let next_wait:Result<Duration, RecvTimeoutError> = match due {
+				Some(due) => waits.recv_timeout(due.saturating_duration_since(Instant::now())),
+				None => waits.recv().map_err(|_| RecvTimeoutError::Disconnected)
+			};
+			match next_wait {
+				Ok(wait) => due = Some(Instant::now() + wait),
+				Err(RecvTimeoutError::Timeout) => {
+					due = None;
+					remote.0.add(|system| system.apply_pending_focus());
+				},
+				Err(RecvTimeoutError::Disconnected) => return
+			}
+		}
+	});
+	sender
+}
+
+/// Run an action given to a remote on a profile, giving a panic in the action to the error handler.
+fn run_remote_action<Action:FnOnce(&mut dyn WindowRelativeProfile)>(handler_guard:&HandlerGuard, key:FaultKey, window:&ActiveWindowInfo, profile:&mut dyn WindowRelativeProfile, action:Action) {
+	let profile_name:String = profile.name().to_string();
+	handler_guard.check(key, &profile_name, ProfilePhase::RemoteAction, window, || action(profile));
+}
+
+/// The windows ignored by default.
+/// The Windows alt-tab switcher briefly receives focus while switching windows.
+fn default_ignored_windows() -> Vec<WindowMatcher> {
+	vec![
+		WindowMatcher::all(vec![
+			WindowMatcher::process_name("explorer.exe"),
+			WindowMatcher::any(vec![WindowMatcher::class("ForegroundStaging"), WindowMatcher::class("XamlExplorerHostIslandWindow")])
+		])
+	]
+}
+
+/// Whether or not the given window is matched by any of the ignored windows matchers.
+fn is_window_ignored(ignored_windows:&Mutex<Vec<WindowMatcher>>, window:&ActiveWindowInfo) -> bool {
+	ignored_windows.lock().unwrap().iter().any(|matcher| matcher.matches(window))
+}
+
+
+
+impl Drop for WindowRelativeSystem {
+	fn drop(&mut self) {
+
+		// Make sure the event source stops feeding a system that no longer exists.
+		if self.running && let Some(event_source) = &mut self.event_source {
+			event_source.stop();
+		}
+	}
 }
 
 
 
 #[derive(Clone)]
-pub struct WindowRelativeSystemRemoteControl(ModificationsQueueRemote<WindowRelativeSystem>);
+pub struct WindowRelativeSystemRemoteControl(ModificationsQueueRemote<WindowRelativeSystem>, Arc<Mutex<Vec<WindowMatcher>>>, Arc<WindowChangeCounters>);
 impl WindowRelativeSystemRemoteControl {
 
+	/* PROPERTY GETTER METHODS */
+
+	/// Whether or not the given window is ignored by the system.
+	/// Event sources can use this to skip work for windows that would be dropped anyway.
+	pub fn is_window_ignored(&self, window:&ActiveWindowInfo) -> bool {
+		is_window_ignored(&self.1, window)
+	}
+
+
+
 	/* STATE CHANGING METHODS */
 
+	/// Stop the system.
+	/// Makes `run` return once all previously queued modifications have been handled.
+	pub fn stop(&self) {
+		self.0.add(|system| {
+			system.stop();
+		});
+	}
+
 	/// Handle a window-change.
-	pub fn handle_window_change(&self, previous_window:&Option<WindowController>, current_window:&WindowController) {
+	/// Changes to ignored windows are dropped.
+	/// When the system is busy, only the latest queued window change is handled, so profiles of windows the user already left are never activated.
+	pub fn handle_window_change(&self, current_window:&ActiveWindowInfo) {
+		self.queue_window_change(current_window, true);
+	}
+
+	/// Handle a window-change that is never skipped in favor of newer window changes.
+	/// Used by sources that replay every change on purpose.
+	pub(crate) fn handle_window_change_in_order(&self, current_window:&ActiveWindowInfo) {
+		self.queue_window_change(current_window, false);
+	}
+
+	/// Queue a window-change, dropping it if the window is ignored.
+	/// Coalesced changes are skipped when a newer coalesced change is queued before they are handled.
+	fn queue_window_change(&self, current_window:&ActiveWindowInfo, coalesce:bool) {
+		if self.is_window_ignored(current_window) {
+			return;
+		}
+		let current_window:ActiveWindowInfo = current_window.clone();
+		let window_changes:Arc<WindowChangeCounters> = self.2.clone();
+		let sequence:Option<usize> = if coalesce { Some(window_changes.latest.fetch_add(1, Ordering::SeqCst) + 1) } else { None };
+		self.0.add(move |system| {
+			if sequence.is_some_and(|sequence| window_changes.latest.load(Ordering::SeqCst) != sequence) {
+				window_changes.coalesced.fetch_add(1, Ordering::SeqCst);
+				return;
+			}
+			window_changes.handled.fetch_add(1, Ordering::SeqCst);
+			system.set_active_window(&current_window);
+		});
+	}
+
+	/// Make the profile with the given name the current profile until another window is focused.
+	pub fn override_current_profile(&self, name:&str) {
+		let name:String = name.to_string();
+		self.0.add(move |system| {
+			system.override_current_profile(&name);
+		});
+	}
+
+	/// Reset a faulted profile, allowing it to be activated again.
+	pub fn reset_faulted_profile(&self, name:&str) {
+		let name:String = name.to_string();
+		self.0.add(move |system| {
+			system.reset_faulted_profile(&name);
+		});
+	}
 
-		// Using raw pointers for a windows that might not exist anymore is safe, but unreliable.
-		// Most functions the now invalid HWND__ is used for will likely be able to handle invalid HWND__'s.
-		let previous_window_pointer:Option<u64> = previous_window.as_ref().map(|window| window.hwnd() as u64);
-		let current_window_pointer:u64 = current_window.hwnd() as u64;
+	/// Handle a window being closed.
+	pub fn handle_window_closed(&self, window_id:u64) {
 		self.0.add(move |system| {
-			system.set_active_window(
-				&previous_window_pointer.map(|pointer| WindowController::from_hwnd(pointer as *mut _)),
-				&WindowController::from_hwnd(current_window_pointer as *mut _)
-			);
+			system.close_window(window_id);
+		});
+	}
+
+	/// Handle a process exiting.
+	pub fn handle_process_exited(&self, pid:u32) {
+		self.0.add(move |system| {
+			system.close_process(pid);
 		});
 	}
 
@@ -226,51 +1243,68 @@ impl WindowRelativeSystemRemoteControl {
 
 	/* EXECUTION METHODS */
 
-	/// Execute an event on the active profile.
+	/// Execute an event on the stack of active profiles, in order of priority.
 	pub fn trigger_event(&self, event_name:&str) {
 		let event_name:String = event_name.to_string();
 		self.0.add(move |system| {
-			system.trigger_event(&event_name);
+			system.trigger_event_in_phase(&event_name, ProfilePhase::RemoteEvent);
 		});
 	}
 
 	/// Execute an action on all profiles.
-	/// Includes the default profile.
+	/// Includes the default profile. Panics in the action are given to the error handler, like panics in handlers.
 	pub fn execute_on_all_profiles<Action:Fn(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, action:Action) {
 		self.0.add(move |system| {
-			system.execute_on_all_profiles(action);
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			system.execute_keyed_on_all_profiles(|key, profile| run_remote_action(&handler_guard, key, &active_window, profile, &action));
+		});
+	}
+
+	/// Execute an action on all active profiles, in order of priority.
+	/// Includes overlay profiles. Panics in the action are given to the error handler, like panics in handlers.
+	pub fn execute_on_active_profiles<Action:Fn(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, action:Action) {
+		self.0.add(move |system| {
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			system.execute_keyed_on_active_profiles(|key, profile| run_remote_action(&handler_guard, key, &active_window, profile, &action));
 		});
 	}
 
 	/// Execute an action on the currently activated profile.
-	/// Uses the default profile if no profile is active.
+	/// Uses the default profile if no profile is active. Never uses overlay profiles. Panics in the action are given to the error handler, like panics in handlers.
 	pub fn execute_on_current_profile<Action:FnOnce(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, action:Action) {
 		self.0.add(move |system| {
-			action(system.profile_with_index_mut(system.active_profile_index));
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			let key:FaultKey = system.fault_key(system.current_profile_index(), active_window.window_id);
+			system.execute_on_current_profile(|profile| run_remote_action(&handler_guard, key, &active_window, profile, action));
 		});
 	}
 
 	/// Execute an action on the default profile.
+	/// Panics in the action are given to the error handler, like panics in handlers.
 	pub fn execute_on_default_profile<Action:FnOnce(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, action:Action) {
 		self.0.add(move |system| {
-			action(&mut *system.default_profile);
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			system.execute_on_default_profile(|profile| run_remote_action(&handler_guard, FaultKey::default(), &active_window, profile, action));
 		});
 	}
 
 	/// Execute an action on the profile with the given name.
-	/// Does nothing if the profile does not exist.
+	/// Does nothing if the profile does not exist. Panics in the action are given to the error handler, like panics in handlers.
 	pub fn execute_on_profile_with_name<Action:Fn(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, name:&str, action:Action){
 		let name:String = name.to_string();
 		self.0.add(move |system| {
-			if name == system.default_profile.name() {
-				action(&mut *system.default_profile);
-			} else {
-				for profile in &mut system.profiles {
-					if profile.name() == name {
-						action(&mut **profile);
-					}
-				}
-			}
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			system.execute_keyed_on_profile_with_name(&name, |key, profile| run_remote_action(&handler_guard, key, &active_window, profile, action));
+		});
+	}
+
+	/// Execute an action on all live instances of the per-window profile with the given name.
+	/// Panics in the action are given to the error handler, like panics in handlers.
+	pub fn execute_on_window_instances<Action:Fn(&mut dyn WindowRelativeProfile) + Send + Sync + 'static>(&self, name:&str, action:Action) {
+		let name:String = name.to_string();
+		self.0.add(move |system| {
+			let (handler_guard, active_window) = (system.handler_guard.clone(), system.active_window.clone());
+			system.execute_keyed_on_window_instances(&name, |key, profile| run_remote_action(&handler_guard, key, &active_window, profile, &action));
 		});
 	}
 }
\ No newline at end of file
@@ -1,33 +1,83 @@
 #[cfg(test)]
-mod tests {
-	use crate::{ WindowRelativeProfileStatus, TaskSystem, WindowRelativeProfile, WindowRelativeProfileEssentials, WindowRelativeSystem, WindowRelativeSystemRemoteControl };
-	use std::{ sync::Mutex, thread::{ self, sleep }, time::Duration };
+pub(crate) mod tests {
+	use crate::{ ActiveWindowInfo, EventPropagation, FakeClock, ProfileActivationMode, ScriptedWindowSource, WindowEventSource, WindowGeometry, WindowMatcher, WindowRelativeProfileStatus, TaskSystem, WindowRelativeProfile, WindowRelativeSystem, WindowRelativeSystemRemoteControl, implement_window_relative_profile_essentials };
+	use std::{ error::Error, sync::{ Arc, Barrier, Mutex, atomic::{ AtomicBool, AtomicUsize, Ordering }, mpsc::{ self, Sender } }, thread::{ self, JoinHandle, sleep }, time::{ Duration, Instant } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
 	
 
-	struct WindowRelativeProfileCore {
+	pub(crate) struct WindowRelativeProfileCore {
 		name:&'static str,
 		process_name:&'static str,
 		task_system:TaskSystem,
-		status:WindowRelativeProfileStatus
+		status:WindowRelativeProfileStatus,
+		priority:i32,
+		activation_delay:Duration,
+		deactivation_grace:Duration,
+		custom_matching:bool,
+		overlay_matcher:Option<WindowMatcher>,
+		event_propagation:EventPropagation,
+		log:Option<Arc<Mutex<Vec<String>>>>
 	}
-	impl WindowRelativeProfileEssentials for WindowRelativeProfileCore {
-		fn name(&self) -> &str { self.name }
-		fn process_name(&self) -> &str { self.process_name}
-		fn task_system(&self) -> &TaskSystem { &self.task_system }
-		fn task_system_mut(&mut self) -> &mut TaskSystem { &mut self.task_system }
-		fn status(&self) -> &WindowRelativeProfileStatus { &self.status }
-		fn status_mut(&mut self) -> &mut WindowRelativeProfileStatus { &mut self.status }
+	implement_window_relative_profile_essentials!(WindowRelativeProfileCore);
+	impl WindowRelativeProfile for WindowRelativeProfileCore {
+		fn priority(&self) -> i32 { self.priority }
+		fn activation_delay(&self) -> Duration { self.activation_delay }
+		fn deactivation_grace(&self) -> Duration { self.deactivation_grace }
+		fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool { active_window.process_name.starts_with(self.process_name) }
+		fn matcher(&self) -> Option<WindowMatcher> { if self.custom_matching { None } else { Some(self.overlay_matcher.clone().unwrap_or_else(|| WindowMatcher::process_name(self.process_name))) } }
+		fn is_overlay(&self) -> bool { self.overlay_matcher.is_some() }
+		fn execute_layered_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<EventPropagation, Box<dyn Error>> {
+			self.execute_event(window, event_name)?;
+			Ok(self.event_propagation)
+		}
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			if let Some(log) = &self.log {
+				log.lock().unwrap().push(format!("{}:{}", self.name, event_name));
+			}
+			Ok(())
+		}
 	}
-	impl WindowRelativeProfile for WindowRelativeProfileCore {}
 	impl WindowRelativeProfileCore {
-		fn new(name:&'static str, process_name:&'static str) -> WindowRelativeProfileCore {
+		pub(crate) fn new(name:&'static str, process_name:&'static str) -> WindowRelativeProfileCore {
 			WindowRelativeProfileCore {
 				name,
 				process_name,
 				task_system: TaskSystem::new(),
-				status: WindowRelativeProfileStatus::default()
+				status: WindowRelativeProfileStatus::default(),
+				priority: 0,
+				activation_delay: Duration::ZERO,
+				deactivation_grace: Duration::ZERO,
+				custom_matching: false,
+				overlay_matcher: None,
+				event_propagation: EventPropagation::Continue,
+				log: None
 			}
 		}
+		fn with_priority(mut self, priority:i32) -> Self {
+			self.priority = priority;
+			self
+		}
+		fn with_hysteresis(mut self, activation_delay:Duration, deactivation_grace:Duration) -> Self {
+			self.activation_delay = activation_delay;
+			self.deactivation_grace = deactivation_grace;
+			self
+		}
+		fn with_custom_matching(mut self) -> Self {
+			self.custom_matching = true;
+			self
+		}
+		fn with_overlay(mut self, matcher:WindowMatcher) -> Self {
+			self.overlay_matcher = Some(matcher);
+			self
+		}
+		fn with_event_propagation(mut self, event_propagation:EventPropagation) -> Self {
+			self.event_propagation = event_propagation;
+			self
+		}
+		pub(crate) fn with_log(mut self, log:&Arc<Mutex<Vec<String>>>) -> Self {
+			self.log = Some(log.clone());
+			self
+		}
 	}
 
 
@@ -41,6 +91,15 @@ mod tests {
 			.with_profile(WindowRelativeProfileCore::new(SECONDARY_PROFILE_NAME, SECONDARY_PROFILE_PROCESS_NAME))
 	}
 
+	/// Poll the system until the condition holds, giving up after two seconds.
+	pub(crate) fn poll_until<Condition:Fn(&WindowRelativeSystem) -> bool>(system:&mut WindowRelativeSystem, condition:Condition) {
+		let start:Instant = Instant::now();
+		while !condition(system) && start.elapsed() < Duration::from_secs(2) {
+			system.poll();
+			thread::sleep(Duration::from_millis(1));
+		}
+	}
+
 
 
 	/* EXECUTION METHODS TESTS */
@@ -86,6 +145,508 @@ mod tests {
 
 
 
+	/* STEPPED EXECUTION TESTS */
+
+	#[test]
+	fn test_system_poll_processes_queued_modifications() {
+		let mut system:WindowRelativeSystem = test_system();
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		let visited:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let visited_remote:Arc<Mutex<Vec<String>>> = visited.clone();
+		remote.execute_on_all_profiles(move |profile| visited_remote.lock().unwrap().push(profile.name().to_string()));
+		assert_eq!(system.poll(), 1);
+		assert_eq!(*visited.lock().unwrap(), vec![DEFAULT_PROFILE_NAME, SECONDARY_PROFILE_NAME]);
+		assert_eq!(system.poll(), 0);
+		assert!(system.is_running());
+	}
+
+	#[test]
+	fn test_system_run_until_idle_processes_nested_modifications() {
+		let mut system:WindowRelativeSystem = test_system();
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		let nested_remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		remote.execute_on_default_profile(move |_| nested_remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, "")));
+		assert_eq!(system.run_until_idle(), 2);
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), SECONDARY_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_system_coalesces_queued_window_changes() {
+		let mut system:WindowRelativeSystem = test_system().with_startup_activation(false);
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+
+		// When multiple changes are queued before the system gets to them, only the latest is handled.
+		remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		remote.handle_window_change(&ActiveWindowInfo::new(2, "other.exe", ""));
+		remote.handle_window_change(&ActiveWindowInfo::new(3, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		assert_eq!(system.run_until_idle(), 3);
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+		assert_eq!(system.active_window().window_id, 3);
+		assert_eq!(system.handled_window_changes(), 1);
+		assert_eq!(system.coalesced_window_changes(), 2);
+
+		// Changes handled one at a time are never coalesced.
+		remote.handle_window_change(&ActiveWindowInfo::new(2, "other.exe", ""));
+		system.run_until_idle();
+		remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		system.run_until_idle();
+		assert_eq!(system.active_window().window_id, 1);
+		assert_eq!(system.handled_window_changes(), 3);
+		assert_eq!(system.coalesced_window_changes(), 2);
+	}
+
+	#[test]
+	fn test_running_system_coalesces_changes_queued_while_busy() {
+		struct BlockingProfile {
+			name:&'static str,
+			process_name:&'static str,
+			task_system:TaskSystem,
+			status:WindowRelativeProfileStatus,
+			barrier:Arc<Barrier>
+		}
+		implement_window_relative_profile_essentials!(BlockingProfile);
+		impl WindowRelativeProfile for BlockingProfile {
+			fn on_activate(&mut self, _window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+				self.barrier.wait();
+				self.barrier.wait();
+				Ok(())
+			}
+		}
+
+		// Keep the system busy activating the blocking profile while two more changes are queued.
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let barrier:Arc<Barrier> = Arc::new(Barrier::new(2));
+		let mut system:WindowRelativeSystem = recording_system(&ScriptedWindowSource::new(), &log)
+			.with_profile(BlockingProfile { name: "blocking", process_name: "blocking.exe", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), barrier: barrier.clone() })
+			.with_startup_activation(false);
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		let system_thread:JoinHandle<WindowRelativeSystem> = thread::spawn(move || {
+			system.run();
+			system
+		});
+		remote.handle_window_change(&scripted_window(1, "blocking.exe"));
+		barrier.wait();
+		remote.handle_window_change(&scripted_window(2, "editor.exe"));
+		remote.handle_window_change(&scripted_window(3, "browser.exe"));
+		barrier.wait();
+		remote.stop();
+		let system:WindowRelativeSystem = system_thread.join().unwrap();
+		assert_eq!(system.handled_window_changes(), 2);
+		assert_eq!(system.coalesced_window_changes(), 1);
+		assert_eq!(*log.lock().unwrap(), vec!["browser:open", "browser:activate", "browser:deactivate", "browser:close"]);
+	}
+
+	#[test]
+	fn test_system_run_for_returns_after_duration() {
+		let mut system:WindowRelativeSystem = test_system();
+		let start:Instant = Instant::now();
+		assert_eq!(system.run_for(Duration::from_millis(20)), 0);
+		assert!(start.elapsed() >= Duration::from_millis(20));
+		assert!(start.elapsed() < Duration::from_millis(500));
+	}
+
+	#[test]
+	fn test_system_stop_while_polling_shuts_down() {
+		let mut system:WindowRelativeSystem = test_system();
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		remote.stop();
+		assert_eq!(system.run_for(Duration::from_secs(5)), 2);
+		assert!(!system.is_running());
+		assert!(system.execute_on_all_profiles(|profile| profile.status() == &WindowRelativeProfileStatus::Uninitialized).into_iter().all(|uninitialized| uninitialized));
+	}
+
+
+
+	/* PRIORITY TESTS */
+
+	#[test]
+	fn test_system_activates_highest_priority_profile() {
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new(DEFAULT_PROFILE_NAME, DEFAULT_PROFILE_PROCESS_NAME))
+			.with_profile(WindowRelativeProfileCore::new("low", SECONDARY_PROFILE_PROCESS_NAME).with_priority(-1))
+			.with_profile(WindowRelativeProfileCore::new("high", SECONDARY_PROFILE_PROCESS_NAME).with_priority(5))
+			.with_profile(WindowRelativeProfileCore::new("normal", SECONDARY_PROFILE_PROCESS_NAME));
+		system.set_active_window(&ActiveWindowInfo::new(0, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "high");
+	}
+
+	#[test]
+	fn test_system_priority_ties_are_independent_of_adding_order() {
+		for names in [["b", "a", "c"], ["c", "b", "a"], ["a", "c", "b"]] {
+			let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new(DEFAULT_PROFILE_NAME, DEFAULT_PROFILE_PROCESS_NAME));
+			for name in names {
+				system.add_profile(WindowRelativeProfileCore::new(name, SECONDARY_PROFILE_PROCESS_NAME));
+			}
+			system.set_active_window(&ActiveWindowInfo::new(0, SECONDARY_PROFILE_PROCESS_NAME, ""));
+			assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "a");
+			assert_eq!(system.shadowed_profiles(), vec![("b".to_string(), "a".to_string()), ("c".to_string(), "a".to_string())]);
+		}
+	}
+
+	#[test]
+	fn test_system_adding_profile_keeps_active_profile() {
+		let mut system:WindowRelativeSystem = test_system();
+		system.set_active_window(&ActiveWindowInfo::new(0, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		system.add_profile(WindowRelativeProfileCore::new("other", "other_process_name").with_priority(1));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), SECONDARY_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_system_lists_shadowed_profiles() {
+		let system:WindowRelativeSystem = test_system()
+			.with_profile(WindowRelativeProfileCore::new("shadowed", SECONDARY_PROFILE_PROCESS_NAME).with_priority(-1))
+			.with_profile(WindowRelativeProfileCore::new("unrelated", "unrelated_process_name"))
+			.with_profile(WindowRelativeProfileCore::new("custom", SECONDARY_PROFILE_PROCESS_NAME).with_priority(-2).with_custom_matching())
+			.with_profile(WindowRelativeProfileCore::new("custom_high", SECONDARY_PROFILE_PROCESS_NAME).with_priority(5).with_custom_matching());
+		assert_eq!(system.shadowed_profiles(), vec![("shadowed".to_string(), SECONDARY_PROFILE_NAME.to_string())]);
+	}
+
+	#[test]
+	fn test_system_uses_custom_matching() {
+		let mut system:WindowRelativeSystem = test_system().with_profile(WindowRelativeProfileCore::new("custom_prefix", "unrelated").with_custom_matching());
+		system.set_active_window(&ActiveWindowInfo::new(0, "unrelated_process_name", ""));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "custom_prefix");
+		assert!(system.describe_matchers().starts_with("custom_prefix: (custom)\n"));
+	}
+
+
+
+	/* HYSTERESIS TESTS */
+
+	fn current_profile_name(system:&mut WindowRelativeSystem) -> String {
+		system.execute_on_current_profile(|profile| profile.name().to_string())
+	}
+
+	#[test]
+	fn test_system_waits_for_activation_delay() {
+		let clock:FakeClock = FakeClock::new();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new(DEFAULT_PROFILE_NAME, DEFAULT_PROFILE_PROCESS_NAME))
+			.with_profile(WindowRelativeProfileCore::new(SECONDARY_PROFILE_NAME, SECONDARY_PROFILE_PROCESS_NAME).with_hysteresis(Duration::from_millis(100), Duration::ZERO))
+			.with_clock(clock.clone());
+		system.set_active_window(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		clock.advance(Duration::from_millis(60));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+
+		// Focusing another window restarts the delay.
+		system.set_active_window(&ActiveWindowInfo::new(2, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		clock.advance(Duration::from_millis(60));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+
+		// Title changes of the same window do not.
+		system.set_active_window(&ActiveWindowInfo::new(2, SECONDARY_PROFILE_PROCESS_NAME, "changed"));
+		clock.advance(Duration::from_millis(40));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_system_keeps_profile_active_during_grace_period() {
+		let clock:FakeClock = FakeClock::new();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new(DEFAULT_PROFILE_NAME, DEFAULT_PROFILE_PROCESS_NAME))
+			.with_profile(WindowRelativeProfileCore::new(SECONDARY_PROFILE_NAME, SECONDARY_PROFILE_PROCESS_NAME).with_hysteresis(Duration::ZERO, Duration::from_millis(100)))
+			.with_clock(clock.clone());
+		system.set_active_window(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+
+		// A quick alt-tab through other windows never deactivates the profile.
+		system.set_active_window(&ActiveWindowInfo::new(2, "explorer.exe", "staging"));
+		clock.advance(Duration::from_millis(60));
+		system.set_active_window(&ActiveWindowInfo::new(3, "toast.exe", "notification"));
+		clock.advance(Duration::from_millis(30));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+		system.set_active_window(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		clock.advance(Duration::from_millis(200));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+		assert_eq!(system.active_window().window_id, 1);
+
+		// The grace period counts from the first focus loss.
+		system.set_active_window(&ActiveWindowInfo::new(2, "explorer.exe", ""));
+		clock.advance(Duration::from_millis(60));
+		system.set_active_window(&ActiveWindowInfo::new(3, "other.exe", ""));
+		clock.advance(Duration::from_millis(40));
+		system.apply_pending_focus();
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+		assert_eq!(system.active_window().window_id, 3);
+	}
+
+	#[test]
+	fn test_running_system_applies_pending_focus() {
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new(DEFAULT_PROFILE_NAME, DEFAULT_PROFILE_PROCESS_NAME))
+			.with_profile(WindowRelativeProfileCore::new(SECONDARY_PROFILE_NAME, SECONDARY_PROFILE_PROCESS_NAME).with_hysteresis(Duration::from_millis(20), Duration::ZERO))
+			.with_startup_activation(false);
+		system.create_remote().handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		system.run_until_idle();
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+		sleep(Duration::from_millis(40));
+		system.run_until_idle();
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_running_system_wakes_up_for_pending_focus() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = recording_system(&ScriptedWindowSource::new(), &log)
+			.with_profile(WindowRelativeProfileCore::new("player", "player.exe").with_log(&log).with_hysteresis(Duration::from_millis(20), Duration::ZERO))
+			.with_startup_activation(false);
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		let system_thread:JoinHandle<WindowRelativeSystem> = thread::spawn(move || {
+			system.run();
+			system
+		});
+
+		// Every change replaces the pending focus, nothing but the timer wakes the system once the last one is due.
+		for _ in 0..5 {
+			remote.handle_window_change(&scripted_window(1, "player.exe"));
+		}
+		let start:Instant = Instant::now();
+		while !log.lock().unwrap().contains(&"player:activate".to_string()) && start.elapsed() < Duration::from_secs(2) {
+			sleep(Duration::from_millis(1));
+		}
+		remote.stop();
+		system_thread.join().unwrap();
+		assert_eq!(*log.lock().unwrap(), vec!["player:open", "player:activate", "player:deactivate", "player:close"]);
+	}
+
+
+
+	/* IGNORED WINDOWS TESTS */
+
+	#[test]
+	fn test_remote_drops_ignored_windows() {
+		let mut system:WindowRelativeSystem = test_system()
+			.with_ignored_windows(WindowMatcher::process_name("overlay.exe"))
+			.with_ignored_windows(WindowMatcher::any(vec![WindowMatcher::tool_window(), WindowMatcher::smaller_than(400, 200)]));
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, ""));
+		remote.handle_window_change(&ActiveWindowInfo::new(2, "overlay.exe", ""));
+		remote.handle_window_change(&ActiveWindowInfo::new(3, "launcher", "").with_tool_window(true));
+		remote.handle_window_change(&ActiveWindowInfo::new(4, "notifications", "").with_geometry(WindowGeometry::new(0, 0, 300, 80)));
+		remote.handle_window_change(&ActiveWindowInfo::new(5, "explorer.exe", "").with_class("ForegroundStaging"));
+		assert_eq!(system.run_until_idle(), 1);
+		assert_eq!(current_profile_name(&mut system), SECONDARY_PROFILE_NAME);
+		assert_eq!(system.active_window().window_id, 1);
+
+		// Without ignore rules, all windows are handled.
+		system.clear_ignored_windows();
+		assert!(system.ignored_windows().is_empty());
+		remote.handle_window_change(&ActiveWindowInfo::new(5, "explorer.exe", "").with_class("ForegroundStaging"));
+		assert_eq!(system.run_until_idle(), 1);
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_system_ignores_startup_window() {
+		let mut system:WindowRelativeSystem = test_system()
+			.with_ignored_windows(WindowMatcher::process_name(SECONDARY_PROFILE_PROCESS_NAME))
+			.with_event_source(ScriptedWindowSource::new().with_current_window(ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, "")));
+		system.run_until_idle();
+		assert_eq!(current_profile_name(&mut system), DEFAULT_PROFILE_NAME);
+		assert!(system.is_window_ignored(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, "")));
+		assert!(!system.is_window_ignored(&ActiveWindowInfo::new(1, DEFAULT_PROFILE_PROCESS_NAME, "")));
+	}
+
+
+
+	/* LAYERED PROFILES TESTS */
+
+	pub(crate) fn recording_system(source:&ScriptedWindowSource, log:&Arc<Mutex<Vec<String>>>) -> WindowRelativeSystem {
+		WindowRelativeSystem::new(WindowRelativeProfileCore::new("default", "default.exe").with_log(log))
+			.with_profile(WindowRelativeProfileCore::new("editor", "editor.exe").with_log(log))
+			.with_profile(WindowRelativeProfileCore::new("browser", "browser.exe").with_log(log))
+			.with_event_source(source.clone())
+	}
+	pub(crate) fn scripted_window(window_id:u64, process_name:&str) -> ActiveWindowInfo {
+		ActiveWindowInfo::new(window_id, process_name, "title").with_pid(window_id as u32).with_class("class")
+	}
+
+	#[test]
+	fn test_system_overlay_stays_active_alongside_window_profile() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log).with_profile(WindowRelativeProfileCore::new("logger", "").with_log(&log).with_overlay(WindowMatcher::process_name_glob("*")).with_priority(10));
+		system.run_until_idle();
+		source.focus(scripted_window(2, "browser.exe"));
+		source.focus(scripted_window(3, "unknown.exe"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"logger:open", "logger:activate", "editor:open", "editor:activate",
+			"editor:deactivate", "logger:window_changed", "browser:open", "browser:activate",
+			"browser:deactivate", "logger:window_changed", "default:open", "default:activate"
+		]);
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "default");
+		assert_eq!(system.execute_on_active_profiles(|profile| profile.name().to_string()), vec!["logger", "default"]);
+	}
+
+	#[test]
+	fn test_system_all_matches_activates_every_matching_profile() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log)
+			.with_profile(WindowRelativeProfileCore::new("tools", "editor.exe").with_log(&log))
+			.with_activation_mode(ProfileActivationMode::AllMatches);
+		system.run_until_idle();
+		source.focus(scripted_window(2, "browser.exe"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:open", "editor:activate", "tools:open", "tools:activate",
+			"editor:deactivate", "tools:deactivate", "browser:open", "browser:activate"
+		]);
+	}
+
+	#[test]
+	fn test_system_events_propagate_through_active_stack() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log)
+			.with_profile(WindowRelativeProfileCore::new("logger", "").with_log(&log).with_overlay(WindowMatcher::process_name_glob("*")).with_priority(10))
+			.with_profile(WindowRelativeProfileCore::new("blocker", "").with_log(&log).with_overlay(WindowMatcher::process_name("browser.exe")).with_priority(20).with_event_propagation(EventPropagation::Stop));
+		system.run_until_idle();
+		log.lock().unwrap().clear();
+		system.trigger_event("save");
+		assert_eq!(*log.lock().unwrap(), vec!["logger:save", "editor:save"]);
+
+		// A higher-priority layer can stop the event from reaching lower layers.
+		source.focus(scripted_window(2, "browser.exe"));
+		system.run_until_idle();
+		log.lock().unwrap().clear();
+		system.trigger_event("save");
+		assert_eq!(*log.lock().unwrap(), vec!["blocker:save"]);
+	}
+
+
+
+	/* WINDOW LIFECYCLE TESTS */
+
+	#[test]
+	fn test_system_window_and_title_changes_within_profile() {
+		struct ChangeProfile {
+			name:&'static str,
+			process_name:&'static str,
+			task_system:TaskSystem,
+			status:WindowRelativeProfileStatus,
+			log:Arc<Mutex<Vec<String>>>
+		}
+		implement_window_relative_profile_essentials!(ChangeProfile);
+		impl WindowRelativeProfile for ChangeProfile {
+			fn on_window_changed(&mut self, previous_window:&ActiveWindowInfo, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+				self.log.lock().unwrap().push(format!("window {} -> {}", previous_window.window_id, window.window_id));
+				Ok(())
+			}
+			fn on_title_changed(&mut self, previous_window:&ActiveWindowInfo, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+				self.log.lock().unwrap().push(format!("title {} -> {}", previous_window.title, window.title));
+				Ok(())
+			}
+			fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+				self.log.lock().unwrap().push(event_name.to_string());
+				Ok(())
+			}
+		}
+
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(ActiveWindowInfo::new(1, "editor.exe", "a.rs"));
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(WindowRelativeProfileCore::new("default", "default.exe").with_log(&log))
+			.with_profile(ChangeProfile { name: "editor", process_name: "editor.exe", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), log: log.clone() })
+			.with_event_source(source.clone());
+		system.run_until_idle();
+		source.focus(ActiveWindowInfo::new(1, "editor.exe", "b.rs"));
+		source.focus(ActiveWindowInfo::new(2, "editor.exe", "b.rs"));
+		source.focus(ActiveWindowInfo::new(2, "editor.exe", "b.rs"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"open", "activate",
+			"title a.rs -> b.rs", "title_changed",
+			"window 1 -> 2", "window_changed"
+		]);
+	}
+
+	#[test]
+	fn test_system_profile_factory_creates_instance_per_window() {
+		struct InstanceProfile {
+			name:&'static str,
+			process_name:&'static str,
+			task_system:TaskSystem,
+			status:WindowRelativeProfileStatus,
+			instance:usize,
+			log:Arc<Mutex<Vec<String>>>
+		}
+		implement_window_relative_profile_essentials!(InstanceProfile);
+		impl WindowRelativeProfile for InstanceProfile {
+			fn on_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+				self.log.lock().unwrap().push(format!("{}:{}:{}", self.instance, event_name, window.window_id));
+				Ok(())
+			}
+		}
+
+		// The first instance is the template and never receives events.
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let instance_count:Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+		let factory_log:Arc<Mutex<Vec<String>>> = log.clone();
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "code.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log)
+			.with_profile_factory(move || InstanceProfile { name: "code", process_name: "code.exe", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), instance: instance_count.fetch_add(1, Ordering::SeqCst), log: factory_log.clone() });
+		system.run_until_idle();
+		source.focus(scripted_window(2, "code.exe"));
+		source.focus(scripted_window(1, "code.exe"));
+		system.run_until_idle();
+		assert_eq!(system.window_instances("code").iter().map(|window| window.window_id).collect::<Vec<u64>>(), vec![1, 2]);
+		assert_eq!(system.execute_on_window_instances("code", |profile| profile.status() == &WindowRelativeProfileStatus::Active), vec![true, false]);
+		assert_eq!(*log.lock().unwrap(), vec![
+			"1:open:1", "1:activate:1",
+			"1:deactivate:1", "2:open:2", "2:activate:2",
+			"2:deactivate:2", "1:activate:1"
+		]);
+
+		// Closing windows closes their instances, deactivating the active one first and falling back to the default profile.
+		log.lock().unwrap().clear();
+		source.close_window(2);
+		source.close_window(1);
+		source.close_window(3);
+		source.focus(scripted_window(3, "code.exe"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"2:close:2",
+			"1:deactivate:1", "1:close:1",
+			"default:open", "default:activate",
+			"default:deactivate", "3:open:3", "3:activate:3"
+		]);
+		assert_eq!(system.window_instances("code").iter().map(|window| window.window_id).collect::<Vec<u64>>(), vec![3]);
+	}
+
+	#[test]
+	fn test_system_closed_windows_and_exited_processes_close_profiles() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let source:ScriptedWindowSource = ScriptedWindowSource::new().with_focus(scripted_window(1, "editor.exe"));
+		let mut system:WindowRelativeSystem = recording_system(&source, &log);
+		system.run_until_idle();
+		source.focus(scripted_window(2, "editor.exe"));
+		source.focus(scripted_window(3, "browser.exe"));
+		system.run_until_idle();
+		log.lock().unwrap().clear();
+
+		// Profiles are closed once all of their windows are gone, active profiles are deactivated first and replaced by the default profile.
+		source.close_window(1);
+		source.exit_process(4);
+		system.run_until_idle();
+		assert!(log.lock().unwrap().is_empty());
+		source.exit_process(2);
+		source.close_window(3);
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec!["editor:close", "browser:deactivate", "browser:close", "default:open", "default:activate"]);
+		assert_eq!(system.execute_on_profile_with_name("browser", |profile| profile.status() == &WindowRelativeProfileStatus::Uninitialized), Some(true));
+
+		// Closed profiles are opened again on their next activation.
+		log.lock().unwrap().clear();
+		source.focus(scripted_window(5, "browser.exe"));
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec!["default:deactivate", "browser:open", "browser:activate"]);
+	}
+
+
+
 	/* MISCELLANEOUS TESTS */
 
 	#[test]
@@ -112,4 +673,40 @@ mod tests {
 		});
 		sleep(Duration::from_millis(500));
 	}
+
+	#[test]
+	fn test_system_starts_custom_event_source() {
+		struct SingleFocusSource(Sender<String>);
+		impl WindowEventSource for SingleFocusSource {
+			fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+				let sender:Sender<String> = self.0.clone();
+				remote.handle_window_change(&ActiveWindowInfo::new(1, SECONDARY_PROFILE_PROCESS_NAME, "secondary_title"));
+				remote.execute_on_current_profile(move |profile| sender.send(profile.name().to_string()).unwrap());
+				Ok(())
+			}
+		}
+
+		let (sender, receiver) = mpsc::channel();
+		let mut system:WindowRelativeSystem = test_system().with_event_source(SingleFocusSource(sender));
+		thread::spawn(move || {
+			system.run();
+		});
+		assert_eq!(receiver.recv_timeout(Duration::from_millis(500)).unwrap(), SECONDARY_PROFILE_NAME);
+	}
+
+	#[test]
+	fn test_dropping_system_stops_event_source() {
+		struct StopTrackingSource(Arc<AtomicBool>);
+		impl WindowEventSource for StopTrackingSource {
+			fn start(&mut self, _remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> { Ok(()) }
+			fn stop(&mut self) { self.0.store(true, Ordering::SeqCst); }
+		}
+
+		let stopped:Arc<AtomicBool> = Arc::new(AtomicBool::new(false));
+		let mut system:WindowRelativeSystem = test_system().with_event_source(StopTrackingSource(stopped.clone()));
+		system.poll();
+		assert!(!stopped.load(Ordering::SeqCst));
+		drop(system);
+		assert!(stopped.load(Ordering::SeqCst));
+	}
 }
\ No newline at end of file
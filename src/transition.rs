@@ -0,0 +1,52 @@
+use crate::ActiveWindowInfo;
+
+
+
+/// The reason the active profiles changed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransitionReason {
+
+	/// Another window received focus or the focused window changed.
+	FocusChange,
+
+	/// The system started while a window was focused.
+	Startup,
+
+	/// A profile was activated by hand, regardless of the focused window.
+	ManualOverride,
+
+	/// All windows of the profile were closed, or their process exited.
+	WindowClosed,
+
+	/// The system shut down.
+	Shutdown
+}
+
+
+
+/// Describes a change of active profiles.
+/// Given to the profiles that are activated or deactivated by the change.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransitionContext {
+	pub reason:TransitionReason,
+	pub previous_profile:Option<String>,
+	pub next_profile:Option<String>,
+	pub previous_window:ActiveWindowInfo,
+	pub next_window:ActiveWindowInfo
+}
+impl TransitionContext {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new transition context.
+	/// The profiles are the names of the current profile before and after the transition, None if no profile was or will be active.
+	pub fn new(reason:TransitionReason, previous_profile:Option<String>, next_profile:Option<String>, previous_window:ActiveWindowInfo, next_window:ActiveWindowInfo) -> TransitionContext {
+		TransitionContext {
+			reason,
+			previous_profile,
+			next_profile,
+			previous_window,
+			next_window
+		}
+	}
+}
\ No newline at end of file
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, ScriptedWindowSource, TaskSystem, TransitionContext, WindowMatcher, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials };
+	use std::{ error::Error, sync::{ Arc, Mutex } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct TransitionProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		overlay:bool,
+		log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(TransitionProfile);
+	impl WindowRelativeProfile for TransitionProfile {
+		fn matcher(&self) -> Option<WindowMatcher> {
+			Some(if self.overlay { WindowMatcher::process_name_glob("*") } else { WindowMatcher::process_name(self.process_name) })
+		}
+		fn is_overlay(&self) -> bool {
+			self.overlay
+		}
+		fn on_transition(&mut self, transition:&TransitionContext) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(format!("{}:transition:{}", self.name, describe_transition(transition)));
+			Ok(())
+		}
+		fn on_transition_event(&mut self, _window:&ActiveWindowInfo, event_name:&str, transition:&TransitionContext) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(format!("{}:{}:{:?}", self.name, event_name, transition.reason));
+			Ok(())
+		}
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(format!("{}:{}", self.name, event_name));
+			Ok(())
+		}
+	}
+	impl TransitionProfile {
+		fn new(name:&'static str, process_name:&'static str, log:&Arc<Mutex<Vec<String>>>) -> TransitionProfile {
+			TransitionProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default(),
+				overlay: false,
+				log: log.clone()
+			}
+		}
+	}
+
+
+
+	fn describe_transition(transition:&TransitionContext) -> String {
+		format!(
+			"{:?} {}->{} {}->{}",
+			transition.reason,
+			transition.previous_profile.as_deref().unwrap_or("-"),
+			transition.next_profile.as_deref().unwrap_or("-"),
+			transition.previous_window.window_id,
+			transition.next_window.window_id
+		)
+	}
+
+	fn transition_system(log:&Arc<Mutex<Vec<String>>>) -> WindowRelativeSystem {
+		WindowRelativeSystem::new(TransitionProfile::new("default", "default.exe", log))
+			.with_profile(TransitionProfile::new("editor", "editor.exe", log))
+			.with_profile(TransitionProfile::new("browser", "browser.exe", log))
+	}
+
+
+
+	#[test]
+	fn test_focus_change_transitions() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = transition_system(&log);
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		system.set_active_window(&ActiveWindowInfo::new(2, "browser.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:transition:FocusChange -->editor 0->1", "editor:open", "editor:activate:FocusChange",
+			"editor:transition:FocusChange editor->browser 1->2", "editor:deactivate:FocusChange",
+			"browser:transition:FocusChange editor->browser 1->2", "browser:open", "browser:activate:FocusChange"
+		]);
+	}
+
+	#[test]
+	fn test_startup_and_shutdown_transitions() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = transition_system(&log).with_event_source(ScriptedWindowSource::new().with_current_window(ActiveWindowInfo::new(1, "editor.exe", "")));
+		system.run_until_idle();
+		system.create_remote().stop();
+		system.run_until_idle();
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:transition:Startup -->editor 0->1", "editor:open", "editor:activate:Startup",
+			"editor:transition:Shutdown editor->- 1->0", "editor:deactivate:Shutdown", "editor:close"
+		]);
+	}
+
+	#[test]
+	fn test_manual_override_transitions() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut overlay:TransitionProfile = TransitionProfile::new("logger", "", &log);
+		overlay.overlay = true;
+		let mut system:WindowRelativeSystem = transition_system(&log).with_profile(overlay);
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		log.lock().unwrap().clear();
+
+		// The override replaces the current profile but keeps overlays, until another window is focused.
+		system.override_current_profile("browser");
+		system.override_current_profile("unknown");
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "browser");
+		assert_eq!(system.execute_on_active_profiles(|profile| profile.name().to_string()), vec!["browser", "logger"]);
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", "title"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "browser");
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:transition:ManualOverride editor->browser 1->1", "editor:deactivate:ManualOverride",
+			"browser:transition:ManualOverride editor->browser 1->1", "browser:open", "browser:activate:ManualOverride",
+			"browser:title_changed", "logger:title_changed"
+		]);
+		log.lock().unwrap().clear();
+		system.set_active_window(&ActiveWindowInfo::new(2, "editor.exe", ""));
+		assert_eq!(*log.lock().unwrap(), vec![
+			"browser:transition:FocusChange browser->editor 1->2", "browser:deactivate:FocusChange",
+			"editor:transition:FocusChange browser->editor 1->2", "editor:activate:FocusChange",
+			"logger:window_changed"
+		]);
+	}
+
+	#[test]
+	fn test_window_closed_transitions() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let mut system:WindowRelativeSystem = transition_system(&log);
+		system.set_active_window(&ActiveWindowInfo::new(1, "editor.exe", ""));
+		log.lock().unwrap().clear();
+		system.close_window(1);
+		assert_eq!(*log.lock().unwrap(), vec![
+			"editor:transition:WindowClosed editor->default 1->1", "editor:deactivate:WindowClosed", "editor:close",
+			"default:transition:WindowClosed editor->default 1->1", "default:open", "default:activate:WindowClosed"
+		]);
+	}
+}
\ No newline at end of file
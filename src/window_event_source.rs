@@ -0,0 +1,35 @@
+use crate::{ ActiveWindowInfo, WindowRelativeSystemRemoteControl };
+use std::error::Error;
+
+
+
+/// A source of window-focus events.
+/// The system starts its source when running, after which the source should forward every focus change through the given remote.
+pub trait WindowEventSource:Send + 'static {
+
+	/// Start listening for window events.
+	/// Focus changes should be forwarded to the system using `remote.handle_window_change`.
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>>;
+
+	/// Stop listening for window events.
+	/// The source should be able to start again afterwards.
+	fn stop(&mut self) {}
+
+	/// Get the window that is currently focused.
+	/// Used to activate the right profile when the system starts running.
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		None
+	}
+}
+
+
+
+/// Get the default window event source for the current platform.
+/// Returns None if the platform has no built-in source.
+pub(crate) fn default_event_source() -> Option<Box<dyn WindowEventSource>> {
+	#[cfg(all(windows, feature="winapi_hook"))]
+	return Some(Box::new(crate::WinEventHookSource::new()));
+
+	#[cfg(not(all(windows, feature="winapi_hook")))]
+	None
+}
\ No newline at end of file
@@ -1,22 +1,123 @@
-use winapi::um::winuser::{DispatchMessageW, GetMessageW, SetWinEventHook, TranslateMessage, EVENT_SYSTEM_FOREGROUND, MSG, WINEVENT_OUTOFCONTEXT};
-use winapi::shared::{ minwindef::DWORD, ntdef::LONG, windef::{ HWINEVENTHOOK, HWINEVENTHOOK__, HWND } };
-use std::{ mem, ptr::null_mut, sync::{ Mutex, MutexGuard }, thread };
-use crate::WindowRelativeSystemRemoteControl;
+use winapi::um::winuser::{DispatchMessageW, GetForegroundWindow, GetMessageW, GetMonitorInfoW, GetWindowLongW, GetWindowRect, GetWindowThreadProcessId, MonitorFromWindow, PostThreadMessageW, SetWinEventHook, TranslateMessage, UnhookWinEvent, EVENT_OBJECT_DESTROY, EVENT_OBJECT_NAMECHANGE, EVENT_SYSTEM_FOREGROUND, GWL_EXSTYLE, MONITORINFO, MONITORINFOEXW, MONITOR_DEFAULTTONEAREST, MSG, OBJID_WINDOW, WINEVENT_OUTOFCONTEXT, WM_QUIT, WS_EX_TOOLWINDOW};
+use winapi::shared::{ minwindef::DWORD, ntdef::LONG, windef::{ HMONITOR, HWINEVENTHOOK, HWINEVENTHOOK__, HWND, RECT } };
+use winapi::um::{ handleapi::CloseHandle, processthreadsapi::{ GetCurrentThreadId, OpenProcess }, winbase::QueryFullProcessImageNameW, winnt::{ HANDLE, PROCESS_QUERY_LIMITED_INFORMATION } };
+use std::{ error::Error, mem, path::PathBuf, ptr::null_mut, sync::{ Mutex, MutexGuard, atomic::{ AtomicU32, AtomicUsize, Ordering } }, thread, time::Duration };
+use crate::{ ActiveWindowInfo, ProcessWatcher, WindowEventSource, WindowGeometry, WindowRelativeSystemRemoteControl };
 use window_controller::WindowController;
 use std::thread::JoinHandle;
 
 
 
 static HOOK_HANDLE:Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
-static mut PREVIOUS_WINDOW:Option<WindowController> = None;
-static REMOTE_CONTROLS:Mutex<Vec<WindowRelativeSystemRemoteControl>> = Mutex::new(Vec::new());
+static HOOK_THREAD_ID:AtomicU32 = AtomicU32::new(0);
+static REMOTE_CONTROLS:Mutex<Vec<(usize, WindowRelativeSystemRemoteControl, ProcessWatcher)>> = Mutex::new(Vec::new());
+static REPORTED_WINDOWS:Mutex<Vec<u64>> = Mutex::new(Vec::new());
+static NEXT_REMOTE_ID:AtomicUsize = AtomicUsize::new(0);
+
+
+
+/// Window event source using the winapi foreground event hook.
+/// All sources share a single hook, each source only receives events while it is registered.
+/// Reports the destruction of reported windows and the exit of their processes.
+#[derive(Default)]
+pub struct WinEventHookSource {
+	registration:Option<HookRegistration>
+}
+impl WinEventHookSource {
+
+	/// Create a new winapi hook source.
+	pub fn new() -> WinEventHookSource {
+		WinEventHookSource::default()
+	}
+}
+impl WindowEventSource for WinEventHookSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+		self.registration = Some(register_remote(remote));
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		self.registration = None;
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		let hwnd:HWND = unsafe { GetForegroundWindow() };
+		if hwnd.is_null() { None } else { Some(window_info(hwnd)) }
+	}
+}
+
+
+
+/// A registered remote that receives hook events.
+/// Unregisters the remote when dropped.
+pub(crate) struct HookRegistration(usize);
+#[cfg(test)]
+impl HookRegistration {
+
+	/// Get the id of the registered remote.
+	pub(crate) fn id(&self) -> usize {
+		self.0
+	}
+}
+impl Drop for HookRegistration {
+	fn drop(&mut self) {
+		unregister_remote(self.0);
+	}
+}
 
 
 
 /// Create a signal trigger.
-pub(crate) fn register_remote(remote:WindowRelativeSystemRemoteControl) {
-	REMOTE_CONTROLS.lock().unwrap().push(remote);
+/// The remote receives events until the returned registration is dropped.
+pub(crate) fn register_remote(remote:WindowRelativeSystemRemoteControl) -> HookRegistration {
+	let remote_id:usize = NEXT_REMOTE_ID.fetch_add(1, Ordering::SeqCst);
+	let process_watcher:ProcessWatcher = ProcessWatcher::start(remote.clone(), ProcessWatcher::DEFAULT_INTERVAL);
+
+	// The window focused at startup was never reported by the hook, register it so its destruction and the exit of its process are reported as well.
+	let hwnd:HWND = unsafe { GetForegroundWindow() };
+	if !hwnd.is_null() {
+		let startup_window:ActiveWindowInfo = window_info(hwnd);
+		if !remote.is_window_ignored(&startup_window) {
+			let mut reported_windows:MutexGuard<'_, Vec<u64>> = REPORTED_WINDOWS.lock().unwrap();
+			if !reported_windows.contains(&startup_window.window_id) {
+				reported_windows.push(startup_window.window_id);
+			}
+			process_watcher.watch(startup_window.pid);
+		}
+	}
+	REMOTE_CONTROLS.lock().unwrap().push((remote_id, remote, process_watcher));
 	launch_hook_if_not_exist();
+	HookRegistration(remote_id)
+}
+
+/// Whether or not the remote with the given registration id currently receives hook events.
+#[cfg(test)]
+pub(crate) fn is_registered(registration_id:usize) -> bool {
+	REMOTE_CONTROLS.lock().unwrap().iter().any(|(id, _, _)| *id == registration_id)
+}
+
+/// Whether or not the hook thread was launched and not shut down since.
+#[cfg(test)]
+pub(crate) fn is_hook_launched() -> bool {
+	HOOK_HANDLE.lock().unwrap().is_some()
+}
+
+/// Remove a signal trigger.
+/// Shuts down the hook once no remotes are left.
+fn unregister_remote(remote_id:usize) {
+	REMOTE_CONTROLS.lock().unwrap().retain(|(id, _, process_watcher)| {
+		if *id == remote_id {
+			process_watcher.stop();
+		}
+		*id != remote_id
+	});
+
+	// Check for remotes again while holding the hook handle, a remote registered in the meantime keeps the hook it found running.
+	// The remotes are not locked while shutting down, as the hook thread locks them to handle its last events.
+	let mut hook_handle:MutexGuard<'_, Option<JoinHandle<()>>> = HOOK_HANDLE.lock().unwrap();
+	if REMOTE_CONTROLS.lock().unwrap().is_empty() {
+		shut_down_hook(&mut hook_handle);
+	}
 }
 
 /// Create a window-hook event callback.
@@ -24,54 +125,145 @@ pub(crate) fn launch_hook_if_not_exist() {
 	let mut hook_handle:MutexGuard<'_, Option<JoinHandle<()>>> = HOOK_HANDLE.lock().unwrap();
 	if hook_handle.is_none() {
 		*hook_handle = Some(thread::spawn(move || unsafe {
+			HOOK_THREAD_ID.store(GetCurrentThreadId(), Ordering::SeqCst);
 
-			// Create and validate hook.
+			// Create and validate hooks.
 			let hook:*mut HWINEVENTHOOK__ = SetWinEventHook(EVENT_SYSTEM_FOREGROUND, EVENT_SYSTEM_FOREGROUND, null_mut(), Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT);
 			if hook.is_null() {
 				eprintln!("Failed to set event hook.");
 				return;
 			}
+			let name_change_hook:*mut HWINEVENTHOOK__ = SetWinEventHook(EVENT_OBJECT_NAMECHANGE, EVENT_OBJECT_NAMECHANGE, null_mut(), Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT);
+			if name_change_hook.is_null() {
+				eprintln!("Failed to set name change event hook, title changes will not be reported.");
+			}
+			let destroy_hook:*mut HWINEVENTHOOK__ = SetWinEventHook(EVENT_OBJECT_DESTROY, EVENT_OBJECT_DESTROY, null_mut(), Some(win_event_proc), 0, 0, WINEVENT_OUTOFCONTEXT);
+			if destroy_hook.is_null() {
+				eprintln!("Failed to set destroy event hook, closed windows will not be reported.");
+			}
 
-			// Figure out initial profile.
-			let current_window:WindowController = WindowController::active();
-			PREVIOUS_WINDOW = Some(current_window);
-
-			// Keep listening for messages on hook.
+			// Keep listening for messages on hook until a quit message is received.
 			let mut msg:MSG = mem::zeroed();
 			while GetMessageW(&mut msg, null_mut(), 0, 0) > 0 {
 				TranslateMessage(&msg);
 				DispatchMessageW(&msg);
 			}
+			UnhookWinEvent(hook);
+			if !name_change_hook.is_null() {
+				UnhookWinEvent(name_change_hook);
+			}
+			if !destroy_hook.is_null() {
+				UnhookWinEvent(destroy_hook);
+			}
 		}));
 	}
 }
 
+/// Stop the window-hook thread if it is running.
+fn shut_down_hook(hook_handle:&mut Option<JoinHandle<()>>) {
+	if let Some(handle) = hook_handle.take() {
 
+		// The thread only accepts messages once its message queue exists, so keep posting until it does.
+		while !handle.is_finished() {
+			let thread_id:DWORD = HOOK_THREAD_ID.load(Ordering::SeqCst);
+			if thread_id != 0 && unsafe { PostThreadMessageW(thread_id, WM_QUIT, 0, 0) } != 0 {
+				break;
+			}
+			thread::sleep(Duration::from_millis(1));
+		}
+		let _ = handle.join();
+		HOOK_THREAD_ID.store(0, Ordering::SeqCst);
+	}
+}
 
-/// Handle a windows hook event to process changes in active window.
-#[allow(static_mut_refs)]
-unsafe extern "system" fn win_event_proc(_event_hook:HWINEVENTHOOK, event:DWORD, hwnd:HWND, _id_object:LONG, _id_child:LONG, _dw_event_thread:DWORD, _dwms_event_time:DWORD) {
-	const ALTTAB_PROCESS_NAME:&str = "explorer.exe";
-	const ALTTAB_CLASS_NAMES:&[&str] = &["ForegroundStaging", "XamlExplorerHostIslandWindow"];
+/// Create a window info snapshot from a window handle.
+pub(crate) fn window_info(hwnd:HWND) -> ActiveWindowInfo {
+	let window:WindowController = WindowController::from_hwnd(hwnd);
+	let mut pid:DWORD = 0;
+	unsafe { GetWindowThreadProcessId(hwnd, &mut pid); }
+	ActiveWindowInfo::new(hwnd as u64, &window.process_name().unwrap_or_default(), &window.title())
+		.with_pid(pid as u32)
+		.with_executable_path(executable_path(pid))
+		.with_class(&window.class())
+		.with_geometry(window_geometry(hwnd))
+		.with_monitor(&monitor_name(hwnd))
+		.with_tool_window(unsafe { GetWindowLongW(hwnd, GWL_EXSTYLE) } as u32 & WS_EX_TOOLWINDOW != 0)
+}
 
+/// Get the executable path of the process with the given id.
+/// Returns an empty path if the process could not be queried.
+fn executable_path(pid:DWORD) -> PathBuf {
 	unsafe {
-		if event == EVENT_SYSTEM_FOREGROUND {
-
-			// Ignore event if the user is alt-tabbing.
-			let current_window:WindowController = WindowController::from_hwnd(hwnd);
-			let process_name:String = current_window.process_name().unwrap_or_default();
-			if process_name == ALTTAB_PROCESS_NAME {
-				let class:String = current_window.class();
-				if ALTTAB_CLASS_NAMES.contains(&class.as_str()) {
-					return;
-				}
+		let process:HANDLE = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, 0, pid);
+		if process.is_null() {
+			return PathBuf::new();
+		}
+		let mut buffer:[u16; 1024] = [0; 1024];
+		let mut length:DWORD = buffer.len() as DWORD;
+		let success:bool = QueryFullProcessImageNameW(process, 0, buffer.as_mut_ptr(), &mut length) != 0;
+		CloseHandle(process);
+		if success { PathBuf::from(String::from_utf16_lossy(&buffer[..length as usize])) } else { PathBuf::new() }
+	}
+}
+
+/// Get the geometry of a window.
+fn window_geometry(hwnd:HWND) -> WindowGeometry {
+	let mut rect:RECT = unsafe { mem::zeroed() };
+	if unsafe { GetWindowRect(hwnd, &mut rect) } == 0 {
+		return WindowGeometry::default();
+	}
+	WindowGeometry::new(rect.left, rect.top, (rect.right - rect.left).max(0) as u32, (rect.bottom - rect.top).max(0) as u32)
+}
+
+/// Get the device name of the monitor a window is on.
+fn monitor_name(hwnd:HWND) -> String {
+	unsafe {
+		let monitor:HMONITOR = MonitorFromWindow(hwnd, MONITOR_DEFAULTTONEAREST);
+		let mut monitor_info:MONITORINFOEXW = mem::zeroed();
+		monitor_info.cbSize = mem::size_of::<MONITORINFOEXW>() as DWORD;
+		if monitor.is_null() || GetMonitorInfoW(monitor, &mut monitor_info as *mut MONITORINFOEXW as *mut MONITORINFO) == 0 {
+			return String::new();
+		}
+		let name_length:usize = monitor_info.szDevice.iter().position(|character| *character == 0).unwrap_or(monitor_info.szDevice.len());
+		String::from_utf16_lossy(&monitor_info.szDevice[..name_length])
+	}
+}
+
+
+
+/// Handle a windows hook event to process changes in active window.
+/// Name changes are only handled for the title of the foreground window itself, not for its child objects.
+/// Destroyed windows are only reported if they were reported as active window before.
+/// Windows ignored by a system are not reported to that system.
+unsafe extern "system" fn win_event_proc(_event_hook:HWINEVENTHOOK, event:DWORD, hwnd:HWND, id_object:LONG, id_child:LONG, _dw_event_thread:DWORD, _dwms_event_time:DWORD) {
+
+	// Report destroyed windows.
+	if event == EVENT_OBJECT_DESTROY {
+		let mut reported_windows:MutexGuard<'_, Vec<u64>> = REPORTED_WINDOWS.lock().unwrap();
+		if id_object == OBJID_WINDOW && id_child == 0 && let Some(window_index) = reported_windows.iter().position(|window_id| *window_id == hwnd as u64) {
+			reported_windows.remove(window_index);
+			for (_, remote_control, _) in &*REMOTE_CONTROLS.lock().unwrap() {
+				remote_control.handle_window_closed(hwnd as u64);
 			}
+		}
+		return;
+	}
 
-			// Update profile in window-relative system.
-			for remote_control in &*REMOTE_CONTROLS.lock().unwrap() {
-				remote_control.handle_window_change(&PREVIOUS_WINDOW, &current_window);
+	let is_title_change:bool = event == EVENT_OBJECT_NAMECHANGE && id_object == OBJID_WINDOW && id_child == 0 && hwnd == unsafe { GetForegroundWindow() };
+	if event == EVENT_SYSTEM_FOREGROUND || is_title_change {
+
+		// Update profile in window-relative systems that do not ignore the window.
+		let current_window:ActiveWindowInfo = window_info(hwnd);
+		let mut reported_windows:MutexGuard<'_, Vec<u64>> = REPORTED_WINDOWS.lock().unwrap();
+		for (_, remote_control, process_watcher) in &*REMOTE_CONTROLS.lock().unwrap() {
+			if remote_control.is_window_ignored(&current_window) {
+				continue;
+			}
+			if !reported_windows.contains(&current_window.window_id) {
+				reported_windows.push(current_window.window_id);
 			}
-			PREVIOUS_WINDOW = Some(current_window);
+			process_watcher.watch(current_window.pid);
+			remote_control.handle_window_change(&current_window);
 		}
 	}
 }
\ No newline at end of file
@@ -1,7 +1,13 @@
 #[cfg(test)]
 mod tests {
-	use std::{ thread::sleep, time::Duration };
-	use crate::window_hook;
+	use std::{ thread::{ self, JoinHandle, sleep }, time::Duration };
+	use crate::{ WindowRelativeProfile, WindowRelativeSystem, WindowRelativeSystemRemoteControl, window_hook, window_relative_profile };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	window_relative_profile!(EmptyProfile, "empty", "");
+	impl WindowRelativeProfile for EmptyProfile {}
+
 
 
 	#[test]
@@ -11,4 +17,36 @@ mod tests {
 		window_hook::launch_hook_if_not_exist();
 		sleep(Duration::from_millis(50));
 	}
+
+	#[test]
+	fn test_hook_registration_is_removed_on_drop() {
+		let system:WindowRelativeSystem = WindowRelativeSystem::new(EmptyProfile::default());
+		let first_registration:window_hook::HookRegistration = window_hook::register_remote(system.create_remote());
+		let second_registration:window_hook::HookRegistration = window_hook::register_remote(system.create_remote());
+		let (first_registration_id, second_registration_id) = (first_registration.id(), second_registration.id());
+		assert!(window_hook::is_registered(first_registration_id));
+		drop(first_registration);
+		assert!(!window_hook::is_registered(first_registration_id));
+		assert!(window_hook::is_registered(second_registration_id));
+		drop(second_registration);
+		assert!(!window_hook::is_registered(second_registration_id));
+	}
+
+	#[test]
+	fn test_hook_keeps_running_for_concurrent_registrations() {
+		let system:WindowRelativeSystem = WindowRelativeSystem::new(EmptyProfile::default());
+		let handles:Vec<JoinHandle<()>> = (0..4).map(|_| {
+			let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+			thread::spawn(move || {
+				for _ in 0..25 {
+					let registration:window_hook::HookRegistration = window_hook::register_remote(remote.clone());
+					assert!(window_hook::is_hook_launched());
+					drop(registration);
+				}
+			})
+		}).collect();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+	}
 }
\ No newline at end of file
@@ -0,0 +1,179 @@
+use std::{ collections::HashMap, path::PathBuf, time::SystemTime };
+
+
+
+/// The position and size of a window in screen coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct WindowGeometry {
+	pub x:i32,
+	pub y:i32,
+	pub width:u32,
+	pub height:u32
+}
+impl WindowGeometry {
+
+	/// Create a new geometry.
+	pub fn new(x:i32, y:i32, width:u32, height:u32) -> WindowGeometry {
+		WindowGeometry { x, y, width, height }
+	}
+}
+
+
+
+/// A platform-independent snapshot of a window that received focus.
+/// Event sources create these once per focus change, the system hands them to profile matching and all profile handlers.
+/// Two snapshots are equal when they describe the same window state, regardless of when they were taken.
+#[derive(Clone, Debug)]
+pub struct ActiveWindowInfo {
+	pub window_id:u64,
+	pub pid:u32,
+	pub process_name:String,
+	pub executable_path:PathBuf,
+	pub title:String,
+	pub class:String,
+	pub app_id:String,
+	pub geometry:WindowGeometry,
+	pub monitor:String,
+	pub is_tool_window:bool,
+	pub timestamp:SystemTime,
+
+	/// The named title regex captures of the matcher of the profile this window activated.
+	/// Filled in by the system, event sources leave this empty.
+	pub title_captures:HashMap<String, String>
+}
+impl ActiveWindowInfo {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Create a new window info, timestamped at the current time.
+	pub fn new(window_id:u64, process_name:&str, title:&str) -> ActiveWindowInfo {
+		ActiveWindowInfo {
+			window_id,
+			process_name: process_name.to_string(),
+			title: title.to_string(),
+			..ActiveWindowInfo::default()
+		}
+	}
+
+	/// Return self with a process id.
+	pub fn with_pid(mut self, pid:u32) -> Self {
+		self.pid = pid;
+		self
+	}
+
+	/// Return self with the path of the executable of the process.
+	pub fn with_executable_path<T:Into<PathBuf>>(mut self, executable_path:T) -> Self {
+		self.executable_path = executable_path.into();
+		self
+	}
+
+	/// Return self with a window class.
+	pub fn with_class(mut self, class:&str) -> Self {
+		self.class = class.to_string();
+		self
+	}
+
+	/// Return self with a Wayland app id.
+	pub fn with_app_id(mut self, app_id:&str) -> Self {
+		self.app_id = app_id.to_string();
+		self
+	}
+
+	/// Return self with a window geometry.
+	pub fn with_geometry(mut self, geometry:WindowGeometry) -> Self {
+		self.geometry = geometry;
+		self
+	}
+
+	/// Return self with the name of the monitor the window is on.
+	pub fn with_monitor(mut self, monitor:&str) -> Self {
+		self.monitor = monitor.to_string();
+		self
+	}
+
+	/// Return self flagged as tool window or not.
+	pub fn with_tool_window(mut self, is_tool_window:bool) -> Self {
+		self.is_tool_window = is_tool_window;
+		self
+	}
+
+	/// Return self with a custom timestamp.
+	pub fn with_timestamp(mut self, timestamp:SystemTime) -> Self {
+		self.timestamp = timestamp;
+		self
+	}
+
+
+
+	/* PROPERTY GETTER METHODS */
+
+	/// Get a named title capture of the matcher of the profile this window activated.
+	pub fn title_capture(&self, name:&str) -> Option<&str> {
+		self.title_captures.get(name).map(|capture| capture.as_str())
+	}
+
+	/// Whether or not both infos describe the same window.
+	/// Ignores properties that can change during the lifetime of a window, like the title.
+	pub fn is_same_window(&self, other:&ActiveWindowInfo) -> bool {
+		self.window_id == other.window_id && self.pid == other.pid && self.process_name == other.process_name
+	}
+}
+impl Default for ActiveWindowInfo {
+	fn default() -> Self {
+		ActiveWindowInfo {
+			window_id: 0,
+			pid: 0,
+			process_name: String::new(),
+			executable_path: PathBuf::new(),
+			title: String::new(),
+			class: String::new(),
+			app_id: String::new(),
+			geometry: WindowGeometry::default(),
+			monitor: String::new(),
+			is_tool_window: false,
+			timestamp: SystemTime::now(),
+			title_captures: HashMap::new()
+		}
+	}
+}
+impl PartialEq for ActiveWindowInfo {
+	fn eq(&self, other:&Self) -> bool {
+		self.window_id == other.window_id &&
+		self.pid == other.pid &&
+		self.process_name == other.process_name &&
+		self.executable_path == other.executable_path &&
+		self.title == other.title &&
+		self.class == other.class &&
+		self.app_id == other.app_id &&
+		self.geometry == other.geometry &&
+		self.monitor == other.monitor &&
+		self.is_tool_window == other.is_tool_window &&
+		self.title_captures == other.title_captures
+	}
+}
+
+
+
+/// Get the name of the process with the given id from procfs.
+/// Uses the file name of the executable, falling back to the command name the kernel cuts to 15 bytes when the executable cannot be read.
+/// Returns an empty string if the process could not be found.
+#[cfg(all(unix, any(feature="x11", feature="sway", feature="hyprland")))]
+pub(crate) fn process_name_of_pid(pid:u32) -> String {
+	if pid == 0 {
+		return String::new();
+	}
+	match executable_path_of_pid(pid).file_name() {
+		Some(file_name) => file_name.to_string_lossy().to_string(),
+		None => std::fs::read_to_string(format!("/proc/{}/comm", pid)).map(|name| name.trim().to_string()).unwrap_or_default()
+	}
+}
+
+/// Get the executable path of the process with the given id from procfs.
+/// Returns an empty path if the process could not be found.
+#[cfg(all(unix, any(feature="x11", feature="sway", feature="hyprland")))]
+pub(crate) fn executable_path_of_pid(pid:u32) -> PathBuf {
+	if pid == 0 {
+		return PathBuf::new();
+	}
+	std::fs::read_link(format!("/proc/{}/exe", pid)).unwrap_or_default()
+}
\ No newline at end of file
@@ -0,0 +1,23 @@
+#[cfg(test)]
+mod tests {
+	use crate::window_info;
+	use std::{ env, path::PathBuf };
+
+
+
+	#[test]
+	fn test_process_name_uses_full_executable_name() {
+		let executable_name:String = env::current_exe().unwrap().file_name().unwrap().to_string_lossy().to_string();
+
+		// Test binaries are named after the crate with a hash appended, longer than the 15 bytes kept by the kernel.
+		assert!(executable_name.len() > 15);
+		assert_eq!(window_info::process_name_of_pid(std::process::id()), executable_name);
+	}
+
+	#[test]
+	fn test_process_name_of_missing_process_is_empty() {
+		assert_eq!(window_info::process_name_of_pid(0), "");
+		assert_eq!(window_info::process_name_of_pid(u32::MAX), "");
+		assert_eq!(window_info::executable_path_of_pid(u32::MAX), PathBuf::new());
+	}
+}
\ No newline at end of file
@@ -0,0 +1,204 @@
+use crate::ActiveWindowInfo;
+use std::{ collections::HashMap, fmt::{ self, Display, Formatter }, path::PathBuf };
+use regex::Regex;
+
+
+
+/// A declarative description of the windows a profile applies to.
+/// Matchers can be combined using `all`, `any` and `not` and are printable for debugging.
+#[derive(Clone, Debug)]
+pub enum WindowMatcher {
+	ProcessName(String),
+	ProcessNameIgnoreCase(String),
+	ProcessNameGlob(String),
+	TitleRegex(Regex),
+	Class(String),
+	ExecutablePath(PathBuf),
+	SmallerThan(u32, u32),
+	ToolWindow,
+	All(Vec<WindowMatcher>),
+	Any(Vec<WindowMatcher>),
+	Not(Box<WindowMatcher>)
+}
+impl WindowMatcher {
+
+	/* CONSTRUCTOR METHODS */
+
+	/// Match windows of which the process name is exactly the given name.
+	pub fn process_name(process_name:&str) -> WindowMatcher {
+		WindowMatcher::ProcessName(process_name.to_string())
+	}
+
+	/// Match windows of which the process name is the given name, ignoring case.
+	pub fn process_name_ignore_case(process_name:&str) -> WindowMatcher {
+		WindowMatcher::ProcessNameIgnoreCase(process_name.to_string())
+	}
+
+	/// Match windows of which the process name matches the given glob pattern.
+	/// Supports '*' for any amount of characters and '?' for a single character.
+	pub fn process_name_glob(pattern:&str) -> WindowMatcher {
+		WindowMatcher::ProcessNameGlob(pattern.to_string())
+	}
+
+	/// Match windows of which the title matches the given regular expression.
+	pub fn title_regex(pattern:&str) -> Result<WindowMatcher, regex::Error> {
+		Ok(WindowMatcher::TitleRegex(Regex::new(pattern)?))
+	}
+
+	/// Match windows with exactly the given window class.
+	pub fn class(class:&str) -> WindowMatcher {
+		WindowMatcher::Class(class.to_string())
+	}
+
+	/// Match windows of which the process runs the given executable.
+	pub fn executable_path<T:Into<PathBuf>>(executable_path:T) -> WindowMatcher {
+		WindowMatcher::ExecutablePath(executable_path.into())
+	}
+
+	/// Match windows of which both the width and height are smaller than the given size.
+	/// Windows without a known size never match.
+	pub fn smaller_than(width:u32, height:u32) -> WindowMatcher {
+		WindowMatcher::SmallerThan(width, height)
+	}
+
+	/// Match windows flagged as tool window by the platform, like floating toolbars and utility popups.
+	pub fn tool_window() -> WindowMatcher {
+		WindowMatcher::ToolWindow
+	}
+
+	/// Match windows that match all given matchers.
+	pub fn all(matchers:Vec<WindowMatcher>) -> WindowMatcher {
+		WindowMatcher::All(matchers)
+	}
+
+	/// Match windows that match any of the given matchers.
+	pub fn any(matchers:Vec<WindowMatcher>) -> WindowMatcher {
+		WindowMatcher::Any(matchers)
+	}
+
+	/// Match windows that do not match the given matcher.
+	#[allow(clippy::should_implement_trait)]
+	pub fn not(matcher:WindowMatcher) -> WindowMatcher {
+		WindowMatcher::Not(Box::new(matcher))
+	}
+
+
+
+	/* USAGE METHODS */
+
+	/// Whether or not the given window matches this matcher.
+	pub fn matches(&self, window:&ActiveWindowInfo) -> bool {
+		match self {
+			WindowMatcher::ProcessName(process_name) => window.process_name == *process_name,
+			WindowMatcher::ProcessNameIgnoreCase(process_name) => window.process_name.eq_ignore_ascii_case(process_name),
+			WindowMatcher::ProcessNameGlob(pattern) => glob_matches(pattern, &window.process_name),
+			WindowMatcher::TitleRegex(regex) => regex.is_match(&window.title),
+			WindowMatcher::Class(class) => window.class == *class,
+			WindowMatcher::ExecutablePath(executable_path) => window.executable_path == *executable_path,
+			WindowMatcher::SmallerThan(width, height) => window.geometry.width != 0 && window.geometry.height != 0 && window.geometry.width < *width && window.geometry.height < *height,
+			WindowMatcher::ToolWindow => window.is_tool_window,
+			WindowMatcher::All(matchers) => matchers.iter().all(|matcher| matcher.matches(window)),
+			WindowMatcher::Any(matchers) => matchers.iter().any(|matcher| matcher.matches(window)),
+			WindowMatcher::Not(matcher) => !matcher.matches(window)
+		}
+	}
+
+	/// Whether or not every window matched by the given matcher is also matched by this matcher.
+	/// This check is conservative, it can return false for matchers that do cover the other matcher.
+	pub fn covers(&self, other:&WindowMatcher) -> bool {
+		match (self, other) {
+			(_, WindowMatcher::All(other_matchers)) if other_matchers.iter().any(|other_matcher| self.covers(other_matcher)) => true,
+			(_, WindowMatcher::Any(other_matchers)) if other_matchers.iter().all(|other_matcher| self.covers(other_matcher)) => true,
+			(WindowMatcher::Any(matchers), _) => matchers.iter().any(|matcher| matcher.covers(other)),
+			(WindowMatcher::All(matchers), _) => matchers.iter().all(|matcher| matcher.covers(other)),
+			(WindowMatcher::ProcessNameIgnoreCase(process_name), WindowMatcher::ProcessName(other_process_name)) => process_name.eq_ignore_ascii_case(other_process_name),
+			(WindowMatcher::ProcessNameGlob(pattern), WindowMatcher::ProcessName(other_process_name)) => glob_matches(pattern, other_process_name),
+			_ => self.to_string() == other.to_string()
+		}
+	}
+
+	/// Get the named captures of all title regexes that contributed to matching the given window.
+	/// Negated matchers never contribute captures.
+	pub fn title_captures(&self, window:&ActiveWindowInfo) -> HashMap<String, String> {
+		let mut captures:HashMap<String, String> = HashMap::new();
+		self.collect_title_captures(window, &mut captures);
+		captures
+	}
+
+	/// Add the named captures of all title regexes that contributed to matching the given window to the given map.
+	fn collect_title_captures(&self, window:&ActiveWindowInfo, captures:&mut HashMap<String, String>) {
+		match self {
+			WindowMatcher::TitleRegex(regex) => {
+				if let Some(regex_captures) = regex.captures(&window.title) {
+					for name in regex.capture_names().flatten() {
+						if let Some(capture) = regex_captures.name(name) {
+							captures.insert(name.to_string(), capture.as_str().to_string());
+						}
+					}
+				}
+			},
+			WindowMatcher::All(matchers) => {
+				for matcher in matchers {
+					matcher.collect_title_captures(window, captures);
+				}
+			},
+			WindowMatcher::Any(matchers) => {
+				if let Some(matcher) = matchers.iter().find(|matcher| matcher.matches(window)) {
+					matcher.collect_title_captures(window, captures);
+				}
+			},
+			_ => {}
+		}
+	}
+}
+impl Display for WindowMatcher {
+	fn fmt(&self, f:&mut Formatter<'_>) -> fmt::Result {
+		match self {
+			WindowMatcher::ProcessName(process_name) => write!(f, "process_name == {:?}", process_name),
+			WindowMatcher::ProcessNameIgnoreCase(process_name) => write!(f, "process_name ==(ignore case) {:?}", process_name),
+			WindowMatcher::ProcessNameGlob(pattern) => write!(f, "process_name like {:?}", pattern),
+			WindowMatcher::TitleRegex(regex) => write!(f, "title =~ /{}/", regex.as_str()),
+			WindowMatcher::Class(class) => write!(f, "class == {:?}", class),
+			WindowMatcher::ExecutablePath(executable_path) => write!(f, "executable_path == {:?}", executable_path),
+			WindowMatcher::SmallerThan(width, height) => write!(f, "size < {}x{}", width, height),
+			WindowMatcher::ToolWindow => write!(f, "tool_window"),
+			WindowMatcher::All(matchers) => write!(f, "all({})", matchers.iter().map(|matcher| matcher.to_string()).collect::<Vec<String>>().join(", ")),
+			WindowMatcher::Any(matchers) => write!(f, "any({})", matchers.iter().map(|matcher| matcher.to_string()).collect::<Vec<String>>().join(", ")),
+			WindowMatcher::Not(matcher) => write!(f, "not({})", matcher)
+		}
+	}
+}
+
+
+
+/// Whether or not the given text matches a glob pattern containing '*' and '?' wildcards.
+pub(crate) fn glob_matches(pattern:&str, text:&str) -> bool {
+	let pattern:Vec<char> = pattern.chars().collect();
+	let text:Vec<char> = text.chars().collect();
+	let mut pattern_index:usize = 0;
+	let mut text_index:usize = 0;
+	let mut last_wildcard:Option<(usize, usize)> = None;
+	while text_index < text.len() {
+		match pattern.get(pattern_index) {
+			Some('*') => {
+				last_wildcard = Some((pattern_index, text_index));
+				pattern_index += 1;
+			},
+			Some(character) if *character == '?' || *character == text[text_index] => {
+				pattern_index += 1;
+				text_index += 1;
+			},
+			_ => match last_wildcard {
+
+				// Let the last wildcard consume one more character and retry.
+				Some((wildcard_pattern_index, wildcard_text_index)) => {
+					last_wildcard = Some((wildcard_pattern_index, wildcard_text_index + 1));
+					pattern_index = wildcard_pattern_index + 1;
+					text_index = wildcard_text_index + 1;
+				},
+				None => return false
+			}
+		}
+	}
+	pattern[pattern_index..].iter().all(|character| *character == '*')
+}
\ No newline at end of file
@@ -0,0 +1,237 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, TaskSystem, WindowGeometry, WindowMatcher, WindowRelativeProfile, WindowRelativeProfileStatus, WindowRelativeSystem, implement_window_relative_profile_essentials, window_matcher, window_relative_profile };
+	use std::{ collections::HashMap, error::Error, sync::{ Arc, Mutex, atomic::{ AtomicUsize, Ordering } } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	struct MatcherProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		matcher:WindowMatcher,
+		matcher_builds:Arc<AtomicUsize>,
+		log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(MatcherProfile);
+	impl WindowRelativeProfile for MatcherProfile {
+		fn matcher(&self) -> Option<WindowMatcher> {
+			self.matcher_builds.fetch_add(1, Ordering::SeqCst);
+			Some(self.matcher.clone())
+		}
+		fn on_event(&mut self, window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(format!("{}:{}", event_name, window.title_capture("caller").unwrap_or("-")));
+			Ok(())
+		}
+	}
+	impl MatcherProfile {
+		fn new(name:&'static str, process_name:&'static str, matcher:WindowMatcher) -> MatcherProfile {
+			MatcherProfile {
+				name,
+				process_name,
+				task_system: TaskSystem::new(),
+				status: WindowRelativeProfileStatus::default(),
+				matcher,
+				matcher_builds: Arc::new(AtomicUsize::new(0)),
+				log: Arc::new(Mutex::new(Vec::new()))
+			}
+		}
+	}
+
+
+
+	struct CapturesProfile {
+		name:&'static str,
+		process_name:&'static str,
+		task_system:TaskSystem,
+		status:WindowRelativeProfileStatus,
+		log:Arc<Mutex<Vec<String>>>
+	}
+	implement_window_relative_profile_essentials!(CapturesProfile);
+	impl WindowRelativeProfile for CapturesProfile {
+		fn matcher(&self) -> Option<WindowMatcher> {
+			Some(WindowMatcher::title_regex(r"^@(?P<caller>[^ ]+) - Discord$").unwrap())
+		}
+		fn on_title_captures_changed(&mut self, window:&ActiveWindowInfo) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(format!("captures:{}", window.title_capture("caller").unwrap_or("-")));
+			Ok(())
+		}
+		fn on_event(&mut self, _window:&ActiveWindowInfo, event_name:&str) -> Result<(), Box<dyn Error>> {
+			self.log.lock().unwrap().push(event_name.to_string());
+			Ok(())
+		}
+	}
+
+
+
+	fn party_matcher() -> WindowMatcher {
+		WindowMatcher::all(vec![
+			WindowMatcher::process_name_ignore_case("applicationframehost.exe"),
+			WindowMatcher::title_regex("(?i)party").unwrap(),
+			WindowMatcher::not(WindowMatcher::class("Settings"))
+		])
+	}
+
+
+
+	#[test]
+	fn test_matcher_leaves() {
+		let window:ActiveWindowInfo = ActiveWindowInfo::new(1, "Code.exe", "main.rs - Visual Studio Code").with_class("Chrome_WidgetWin_1").with_executable_path("C:/Programs/Code.exe");
+		assert!(WindowMatcher::process_name("Code.exe").matches(&window));
+		assert!(!WindowMatcher::process_name("code.exe").matches(&window));
+		assert!(WindowMatcher::process_name_ignore_case("code.EXE").matches(&window));
+		assert!(WindowMatcher::process_name_glob("Co?e.*").matches(&window));
+		assert!(!WindowMatcher::process_name_glob("*.com").matches(&window));
+		assert!(WindowMatcher::title_regex(r"\.rs - Visual Studio Code$").unwrap().matches(&window));
+		assert!(WindowMatcher::class("Chrome_WidgetWin_1").matches(&window));
+		assert!(WindowMatcher::executable_path("C:/Programs/Code.exe").matches(&window));
+		assert!(!WindowMatcher::executable_path("C:/Programs/Other.exe").matches(&window));
+		assert!(WindowMatcher::title_regex("(unclosed").is_err());
+		assert!(!WindowMatcher::tool_window().matches(&window));
+		assert!(WindowMatcher::tool_window().matches(&window.clone().with_tool_window(true)));
+		assert!(!WindowMatcher::smaller_than(400, 200).matches(&window));
+		assert!(WindowMatcher::smaller_than(400, 200).matches(&window.clone().with_geometry(WindowGeometry::new(10, 10, 300, 80))));
+		assert!(!WindowMatcher::smaller_than(400, 200).matches(&window.clone().with_geometry(WindowGeometry::new(10, 10, 300, 1080))));
+	}
+
+	#[test]
+	fn test_matcher_combinators() {
+		let party:ActiveWindowInfo = ActiveWindowInfo::new(1, "ApplicationFrameHost.exe", "Xbox Party");
+		let calculator:ActiveWindowInfo = ActiveWindowInfo::new(2, "ApplicationFrameHost.exe", "Calculator");
+		let settings:ActiveWindowInfo = ActiveWindowInfo::new(3, "ApplicationFrameHost.exe", "Party settings").with_class("Settings");
+		assert!(party_matcher().matches(&party));
+		assert!(!party_matcher().matches(&calculator));
+		assert!(!party_matcher().matches(&settings));
+		let any:WindowMatcher = WindowMatcher::any(vec![WindowMatcher::title_regex("^Calc").unwrap(), WindowMatcher::class("Settings")]);
+		assert!(!any.matches(&party));
+		assert!(any.matches(&calculator));
+		assert!(any.matches(&settings));
+		assert!(WindowMatcher::all(Vec::new()).matches(&party));
+		assert!(!WindowMatcher::any(Vec::new()).matches(&party));
+	}
+
+	#[test]
+	fn test_glob_matching() {
+		assert!(window_matcher::glob_matches("*", ""));
+		assert!(window_matcher::glob_matches("*.exe", "Code.exe"));
+		assert!(window_matcher::glob_matches("a*b*c", "aXXbYYbc"));
+		assert!(window_matcher::glob_matches("???", "abc"));
+		assert!(!window_matcher::glob_matches("???", "ab"));
+		assert!(!window_matcher::glob_matches("a*c", "abcd"));
+		assert!(!window_matcher::glob_matches("", "a"));
+	}
+
+	#[test]
+	fn test_matcher_display() {
+		assert_eq!(party_matcher().to_string(), "all(process_name ==(ignore case) \"applicationframehost.exe\", title =~ /(?i)party/, not(class == \"Settings\"))");
+		assert_eq!(WindowMatcher::any(vec![WindowMatcher::process_name_glob("*.exe"), WindowMatcher::executable_path("/usr/bin/foot")]).to_string(), "any(process_name like \"*.exe\", executable_path == \"/usr/bin/foot\")");
+		assert_eq!(WindowMatcher::any(vec![WindowMatcher::tool_window(), WindowMatcher::smaller_than(400, 200)]).to_string(), "any(tool_window, size < 400x200)");
+	}
+
+	#[test]
+	fn test_system_uses_declared_matchers() {
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(MatcherProfile::new("default", "default.exe", WindowMatcher::process_name("default.exe")))
+			.with_profile(MatcherProfile::new("party", "ApplicationFrameHost.exe", party_matcher()))
+			.with_profile(MatcherProfile::new("terminal", "", WindowMatcher::process_name_glob("*term*")));
+		system.set_active_window(&ActiveWindowInfo::new(1, "ApplicationFrameHost.exe", "Xbox Party"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "party");
+		system.set_active_window(&ActiveWindowInfo::new(2, "ApplicationFrameHost.exe", "Calculator"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "default");
+		system.set_active_window(&ActiveWindowInfo::new(3, "xterm", "Shell"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "terminal");
+		assert_eq!(system.describe_matchers(), format!("party: {}\nterminal: process_name like \"*term*\"\ndefault: (default)", party_matcher()));
+	}
+
+	#[test]
+	fn test_system_calls_overridden_matches_window() {
+		window_relative_profile!(PartyProfile, "party", "ApplicationFrameHost.exe");
+		impl WindowRelativeProfile for PartyProfile {
+			fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+				active_window.process_name == self.process_name && active_window.title.contains("Party")
+			}
+		}
+
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(MatcherProfile::new("default", "default.exe", WindowMatcher::process_name("default.exe"))).with_profile(PartyProfile::default());
+		system.set_active_window(&ActiveWindowInfo::new(1, "ApplicationFrameHost.exe", "Xbox Party"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "party");
+		system.set_active_window(&ActiveWindowInfo::new(2, "ApplicationFrameHost.exe", "Calculator"));
+		assert_eq!(system.execute_on_current_profile(|profile| profile.name().to_string()), "default");
+		assert!(system.describe_matchers().starts_with("party: (custom)\n"));
+	}
+
+	#[test]
+	fn test_system_builds_matchers_once() {
+		let party:MatcherProfile = MatcherProfile::new("party", "ApplicationFrameHost.exe", party_matcher());
+		let matcher_builds:Arc<AtomicUsize> = party.matcher_builds.clone();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(MatcherProfile::new("default", "default.exe", WindowMatcher::process_name("default.exe"))).with_profile(party);
+		for window_id in 0..10 {
+			system.set_active_window(&ActiveWindowInfo::new(window_id, "ApplicationFrameHost.exe", if window_id % 2 == 0 { "Xbox Party" } else { "Calculator" }));
+		}
+		system.describe_matchers();
+		system.shadowed_profiles();
+		assert_eq!(matcher_builds.load(Ordering::SeqCst), 1);
+	}
+
+	#[test]
+	fn test_matcher_title_captures() {
+		let matcher:WindowMatcher = WindowMatcher::all(vec![
+			WindowMatcher::process_name("Discord.exe"),
+			WindowMatcher::title_regex(r"^@(?P<caller>[^ ]+) - Discord$").unwrap(),
+			WindowMatcher::any(vec![WindowMatcher::title_regex("(?P<unused>never)").unwrap(), WindowMatcher::title_regex("(?P<app>Discord)$").unwrap()]),
+			WindowMatcher::not(WindowMatcher::title_regex("(?P<negated>Slack)").unwrap())
+		]);
+		let captures:HashMap<String, String> = matcher.title_captures(&ActiveWindowInfo::new(1, "Discord.exe", "@friend - Discord"));
+		assert_eq!(captures, HashMap::from([("caller".to_string(), "friend".to_string()), ("app".to_string(), "Discord".to_string())]));
+		assert!(WindowMatcher::process_name("Discord.exe").title_captures(&ActiveWindowInfo::new(1, "Discord.exe", "@friend - Discord")).is_empty());
+	}
+
+	#[test]
+	fn test_system_delivers_title_captures() {
+		let discord:MatcherProfile = MatcherProfile::new("discord", "Discord.exe", WindowMatcher::all(vec![
+			WindowMatcher::process_name("Discord.exe"),
+			WindowMatcher::title_regex(r"^(@(?P<caller>[^ ]+) - )?Discord$").unwrap()
+		]));
+		let log:Arc<Mutex<Vec<String>>> = discord.log.clone();
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(MatcherProfile::new("default", "default.exe", WindowMatcher::process_name("default.exe"))).with_profile(discord);
+		system.set_active_window(&ActiveWindowInfo::new(1, "Discord.exe", "@first - Discord"));
+		assert_eq!(system.active_window().title_capture("caller"), Some("first"));
+
+		// Title changes within the same profile re-deliver the captures, unchanged captures are not delivered again.
+		system.set_active_window(&ActiveWindowInfo::new(1, "Discord.exe", "@second - Discord"));
+		system.set_active_window(&ActiveWindowInfo::new(2, "Discord.exe", "@second - Discord"));
+		system.set_active_window(&ActiveWindowInfo::new(1, "Discord.exe", "Discord"));
+		system.set_active_window(&ActiveWindowInfo::new(3, "default.exe", "Other"));
+		assert_eq!(*log.lock().unwrap(), vec![
+			"open:first", "activate:first",
+			"title_changed:second", "title_captures_changed:second",
+			"window_changed:second",
+			"window_changed:-", "title_captures_changed:-",
+			"deactivate:-"
+		]);
+	}
+
+	#[test]
+	fn test_system_delivers_title_captures_to_typed_handler() {
+		let log:Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+		let discord:CapturesProfile = CapturesProfile { name: "discord", process_name: "Discord.exe", task_system: TaskSystem::new(), status: WindowRelativeProfileStatus::default(), log: log.clone() };
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(MatcherProfile::new("default", "default.exe", WindowMatcher::process_name("default.exe"))).with_profile(discord);
+		system.set_active_window(&ActiveWindowInfo::new(1, "Discord.exe", "@first - Discord"));
+		system.set_active_window(&ActiveWindowInfo::new(1, "Discord.exe", "@second - Discord"));
+		assert_eq!(*log.lock().unwrap(), vec!["open", "activate", "title_changed", "captures:second"]);
+	}
+
+	#[test]
+	fn test_matcher_covers() {
+		let code:WindowMatcher = WindowMatcher::process_name("Code.exe");
+		assert!(code.covers(&code));
+		assert!(WindowMatcher::process_name_ignore_case("code.exe").covers(&code));
+		assert!(WindowMatcher::process_name_glob("*.exe").covers(&code));
+		assert!(code.covers(&WindowMatcher::all(vec![code.clone(), WindowMatcher::title_regex("main").unwrap()])));
+		assert!(WindowMatcher::any(vec![WindowMatcher::class("Editor"), code.clone()]).covers(&code));
+		assert!(code.covers(&WindowMatcher::any(vec![code.clone(), WindowMatcher::all(vec![code.clone(), WindowMatcher::class("Editor")])])));
+		assert!(!code.covers(&WindowMatcher::process_name_glob("*.exe")));
+		assert!(!WindowMatcher::all(vec![code.clone(), WindowMatcher::title_regex("main").unwrap()]).covers(&code));
+		assert!(!WindowMatcher::process_name("code.exe").covers(&code));
+	}
+}
\ No newline at end of file
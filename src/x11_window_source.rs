@@ -0,0 +1,222 @@
+use x11rb::{ connection::Connection, protocol::{ Event, randr::{ ConnectionExt as _, MonitorInfo }, xproto::{ Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt, EventMask, GetAtomNameReply, GetGeometryReply, GetPropertyReply, TranslateCoordinatesReply, Window } }, rust_connection::RustConnection };
+use crate::{ ActiveWindowInfo, ProcessWatcher, WindowEventSource, WindowGeometry, WindowRelativeSystemRemoteControl, window_info };
+use std::{ error::Error, sync::{ Arc, atomic::{ AtomicBool, Ordering } }, thread, time::Duration };
+
+
+
+const EVENT_POLL_INTERVAL:Duration = Duration::from_millis(20);
+
+
+
+/// Window event source following the `_NET_ACTIVE_WINDOW` property of the X11 root window.
+/// Closed windows are detected through the `_NET_CLIENT_LIST` property. Requires a window manager that implements EWMH.
+#[derive(Default)]
+pub struct X11WindowSource {
+	display_name:Option<String>,
+	running:Arc<AtomicBool>
+}
+impl X11WindowSource {
+
+	/// Create a new X11 source connecting to the display in the `DISPLAY` environment variable.
+	pub fn new() -> X11WindowSource {
+		X11WindowSource::default()
+	}
+
+	/// Return self connecting to a specific display, like ":1".
+	pub fn with_display(mut self, display_name:&str) -> Self {
+		self.display_name = Some(display_name.to_string());
+		self
+	}
+}
+impl WindowEventSource for X11WindowSource {
+	fn start(&mut self, remote:WindowRelativeSystemRemoteControl) -> Result<(), Box<dyn Error>> {
+
+		// Connect and subscribe to root window property changes.
+		let (connection, screen_index) = x11rb::connect(self.display_name.as_deref())?;
+		let root:Window = connection.setup().roots[screen_index].root;
+		let atoms:X11Atoms = X11Atoms::new(&connection)?;
+		connection.change_window_attributes(root, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE))?;
+		connection.flush()?;
+
+		// Keep listening for active window changes, title changes of the active window and closed windows.
+		// Events are polled rather than awaited, so the thread can notice when the source is stopped.
+		self.running = Arc::new(AtomicBool::new(true));
+		let running:Arc<AtomicBool> = self.running.clone();
+		let process_watcher:ProcessWatcher = ProcessWatcher::start(remote.clone(), ProcessWatcher::DEFAULT_INTERVAL);
+		thread::spawn(move || {
+			let mut watched_window:Window = 0;
+			let mut client_windows:Vec<Window> = client_list(&connection, root, &atoms);
+			if let Some(window) = active_window_info(&connection, root, &atoms) {
+				watch_window(&connection, &mut watched_window, window.window_id as Window);
+				process_watcher.watch(window.pid);
+			}
+			while running.load(Ordering::SeqCst) {
+				match connection.poll_for_event() {
+					Ok(Some(Event::PropertyNotify(event))) if event.window == root && event.atom == atoms.net_active_window => {
+						if let Some(window) = active_window_info(&connection, root, &atoms) {
+							watch_window(&connection, &mut watched_window, window.window_id as Window);
+							process_watcher.watch(window.pid);
+							remote.handle_window_change(&window);
+						}
+					},
+					Ok(Some(Event::PropertyNotify(event))) if event.window == root && event.atom == atoms.net_client_list => {
+						let current_client_windows:Vec<Window> = client_list(&connection, root, &atoms);
+						for closed_window in client_windows.iter().filter(|window| !current_client_windows.contains(window)) {
+							remote.handle_window_closed(*closed_window as u64);
+						}
+						client_windows = current_client_windows;
+					},
+					Ok(Some(Event::PropertyNotify(event))) if event.window == watched_window && (event.atom == atoms.net_wm_name || event.atom == u32::from(AtomEnum::WM_NAME)) => {
+						if let Some(window) = active_window_info(&connection, root, &atoms) {
+							remote.handle_window_change(&window);
+						}
+					},
+					Ok(Some(_)) => {},
+					Ok(None) => thread::sleep(EVENT_POLL_INTERVAL),
+					Err(error) => {
+						eprintln!("X11 window source lost its connection: {:?}", error);
+						break;
+					}
+				}
+			}
+			process_watcher.stop();
+		});
+		Ok(())
+	}
+
+	fn stop(&mut self) {
+		self.running.store(false, Ordering::SeqCst);
+	}
+
+	fn current_window(&mut self) -> Option<ActiveWindowInfo> {
+		let (connection, screen_index) = x11rb::connect(self.display_name.as_deref()).ok()?;
+		let root:Window = connection.setup().roots[screen_index].root;
+		let atoms:X11Atoms = X11Atoms::new(&connection).ok()?;
+		active_window_info(&connection, root, &atoms)
+	}
+}
+
+
+
+/// The atoms required to resolve window information.
+struct X11Atoms {
+	net_active_window:Atom,
+	net_client_list:Atom,
+	net_wm_pid:Atom,
+	net_wm_name:Atom,
+	net_wm_window_type:Atom,
+	tool_window_types:[Atom; 3],
+	utf8_string:Atom
+}
+impl X11Atoms {
+
+	/// Intern all required atoms.
+	fn new(connection:&RustConnection) -> Result<X11Atoms, Box<dyn Error>> {
+		Ok(X11Atoms {
+			net_active_window: connection.intern_atom(false, b"_NET_ACTIVE_WINDOW")?.reply()?.atom,
+			net_client_list: connection.intern_atom(false, b"_NET_CLIENT_LIST")?.reply()?.atom,
+			net_wm_pid: connection.intern_atom(false, b"_NET_WM_PID")?.reply()?.atom,
+			net_wm_name: connection.intern_atom(false, b"_NET_WM_NAME")?.reply()?.atom,
+			net_wm_window_type: connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE")?.reply()?.atom,
+			tool_window_types: [
+				connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE_UTILITY")?.reply()?.atom,
+				connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE_TOOLBAR")?.reply()?.atom,
+				connection.intern_atom(false, b"_NET_WM_WINDOW_TYPE_NOTIFICATION")?.reply()?.atom
+			],
+			utf8_string: connection.intern_atom(false, b"UTF8_STRING")?.reply()?.atom
+		})
+	}
+}
+
+
+
+/// Resolve the currently active window into a window info.
+/// Returns None if no window is active.
+fn active_window_info(connection:&RustConnection, root:Window, atoms:&X11Atoms) -> Option<ActiveWindowInfo> {
+	let window:Window = property_u32(connection, root, atoms.net_active_window, AtomEnum::WINDOW.into())?;
+	if window == 0 {
+		return None;
+	}
+
+	// Read window properties.
+	let pid:u32 = property_u32(connection, window, atoms.net_wm_pid, AtomEnum::CARDINAL.into()).unwrap_or_default();
+	let title:String = property_string(connection, window, atoms.net_wm_name, atoms.utf8_string)
+		.or_else(|| property_string(connection, window, AtomEnum::WM_NAME.into(), AtomEnum::STRING.into()))
+		.unwrap_or_default();
+	let class:String = property_string(connection, window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING.into())
+		.and_then(|classes| classes.split('\0').nth(1).map(|class| class.to_string()))
+		.unwrap_or_default();
+	let is_tool_window:bool = property_u32(connection, window, atoms.net_wm_window_type, AtomEnum::ATOM.into()).is_some_and(|window_type| atoms.tool_window_types.contains(&window_type));
+	let process_name:String = window_info::process_name_of_pid(pid);
+	let geometry:WindowGeometry = window_geometry(connection, root, window).unwrap_or_default();
+	let monitor:String = monitor_name(connection, root, &geometry).unwrap_or_default();
+
+	Some(
+		ActiveWindowInfo::new(window as u64, &process_name, &title)
+			.with_pid(pid)
+			.with_executable_path(window_info::executable_path_of_pid(pid))
+			.with_class(&class)
+			.with_geometry(geometry)
+			.with_monitor(&monitor)
+			.with_tool_window(is_tool_window)
+	)
+}
+
+/// Get the windows managed by the window manager.
+fn client_list(connection:&RustConnection, root:Window, atoms:&X11Atoms) -> Vec<Window> {
+	connection.get_property(false, root, atoms.net_client_list, AtomEnum::WINDOW, 0, u32::MAX).ok()
+		.and_then(|cookie| cookie.reply().ok())
+		.and_then(|reply| reply.value32().map(|windows| windows.collect()))
+		.unwrap_or_default()
+}
+
+/// Subscribe to property changes of the given window instead of the previously watched window.
+/// Errors are ignored, as windows can be destroyed at any moment.
+fn watch_window(connection:&RustConnection, watched_window:&mut Window, window:Window) {
+	if *watched_window == window {
+		return;
+	}
+	if *watched_window != 0 {
+		let _ = connection.change_window_attributes(*watched_window, &ChangeWindowAttributesAux::new().event_mask(EventMask::NO_EVENT));
+	}
+	if window != 0 {
+		let _ = connection.change_window_attributes(window, &ChangeWindowAttributesAux::new().event_mask(EventMask::PROPERTY_CHANGE));
+	}
+	let _ = connection.flush();
+	*watched_window = window;
+}
+
+/// Get the geometry of a window in root window coordinates.
+fn window_geometry(connection:&RustConnection, root:Window, window:Window) -> Option<WindowGeometry> {
+	let geometry:GetGeometryReply = connection.get_geometry(window).ok()?.reply().ok()?;
+	let position:TranslateCoordinatesReply = connection.translate_coordinates(window, root, 0, 0).ok()?.reply().ok()?;
+	Some(WindowGeometry::new(position.dst_x as i32, position.dst_y as i32, geometry.width as u32, geometry.height as u32))
+}
+
+/// Get the name of the RandR monitor containing the center of the given geometry.
+fn monitor_name(connection:&RustConnection, root:Window, geometry:&WindowGeometry) -> Option<String> {
+	let center:(i32, i32) = (geometry.x + geometry.width as i32 / 2, geometry.y + geometry.height as i32 / 2);
+	let monitors:Vec<MonitorInfo> = connection.randr_get_monitors(root, true).ok()?.reply().ok()?.monitors;
+	let monitor:&MonitorInfo = monitors.iter().find(|monitor| {
+		let (x, y) = (monitor.x as i32, monitor.y as i32);
+		center.0 >= x && center.0 < x + monitor.width as i32 && center.1 >= y && center.1 < y + monitor.height as i32
+	})?;
+	let name:GetAtomNameReply = connection.get_atom_name(monitor.name).ok()?.reply().ok()?;
+	Some(String::from_utf8_lossy(&name.name).to_string())
+}
+
+/// Read the first 32-bit value of a window property.
+fn property_u32(connection:&RustConnection, window:Window, property:Atom, property_type:Atom) -> Option<u32> {
+	let reply:GetPropertyReply = connection.get_property(false, window, property, property_type, 0, 1).ok()?.reply().ok()?;
+	reply.value32()?.next()
+}
+
+/// Read a window property as string.
+fn property_string(connection:&RustConnection, window:Window, property:Atom, property_type:Atom) -> Option<String> {
+	let reply:GetPropertyReply = connection.get_property(false, window, property, property_type, 0, u32::MAX).ok()?.reply().ok()?;
+	if reply.value.is_empty() {
+		None
+	} else {
+		Some(String::from_utf8_lossy(&reply.value).to_string())
+	}
+}
\ No newline at end of file
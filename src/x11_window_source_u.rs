@@ -0,0 +1,65 @@
+#[cfg(test)]
+mod tests {
+	use crate::{ ActiveWindowInfo, WindowRelativeProfile, WindowRelativeSystem, WindowRelativeSystemRemoteControl, X11WindowSource, window_relative_profile };
+	use x11rb::{ connection::Connection, protocol::xproto::{ AtomEnum, ConnectionExt, CreateWindowAux, PropMode, Window, WindowClass }, wrapper::ConnectionExt as _, COPY_DEPTH_FROM_PARENT };
+	use std::{ sync::mpsc, thread, time::{ Duration, Instant } };
+	use crate as window_relative_system; // Makes the profile creation macro usable from within the crate.
+
+
+	const TEST_CLASS:&str = "WrsTestClass";
+	const TEST_TITLE:&str = "window relative system test window";
+	
+	window_relative_profile!(DefaultProfile, "default", "");
+	impl WindowRelativeProfile for DefaultProfile {}
+
+	window_relative_profile!(ClassProfile, "class_profile", "");
+	impl WindowRelativeProfile for ClassProfile {
+		fn matches_window(&self, active_window:&ActiveWindowInfo) -> bool {
+			active_window.class == TEST_CLASS && active_window.title == TEST_TITLE && active_window.pid == std::process::id()
+		}
+	}
+
+
+
+	#[test]
+	#[ignore = "needs an X11 display, run with --ignored under Xvfb"]
+	fn test_x11_source_follows_active_window() {
+
+		// Create a test client window with the properties a regular client would set.
+		let (connection, screen_index) = x11rb::connect(None).unwrap();
+		let root:Window = connection.setup().roots[screen_index].root;
+		let window:Window = connection.generate_id().unwrap();
+		connection.create_window(COPY_DEPTH_FROM_PARENT, window, root, 0, 0, 100, 100, 0, WindowClass::INPUT_OUTPUT, 0, &CreateWindowAux::new()).unwrap();
+		let net_active_window = connection.intern_atom(false, b"_NET_ACTIVE_WINDOW").unwrap().reply().unwrap().atom;
+		let net_wm_pid = connection.intern_atom(false, b"_NET_WM_PID").unwrap().reply().unwrap().atom;
+		let net_wm_name = connection.intern_atom(false, b"_NET_WM_NAME").unwrap().reply().unwrap().atom;
+		let utf8_string = connection.intern_atom(false, b"UTF8_STRING").unwrap().reply().unwrap().atom;
+		connection.change_property32(PropMode::REPLACE, window, net_wm_pid, AtomEnum::CARDINAL, &[std::process::id()]).unwrap();
+		connection.change_property8(PropMode::REPLACE, window, AtomEnum::WM_CLASS, AtomEnum::STRING, format!("wrs_test\0{}\0", TEST_CLASS).as_bytes()).unwrap();
+		connection.change_property8(PropMode::REPLACE, window, net_wm_name, utf8_string, TEST_TITLE.as_bytes()).unwrap();
+		connection.flush().unwrap();
+
+		// Run the system on the X11 source.
+		let mut system:WindowRelativeSystem = WindowRelativeSystem::new(DefaultProfile::default())
+			.with_profile(ClassProfile::default())
+			.with_event_source(X11WindowSource::new());
+		let remote:WindowRelativeSystemRemoteControl = system.create_remote();
+		thread::spawn(move || {
+			system.run();
+		});
+
+		// Act as the window manager by activating the window until the system picks it up.
+		let start:Instant = Instant::now();
+		while start.elapsed() < Duration::from_secs(2) {
+			connection.change_property32(PropMode::REPLACE, root, net_active_window, AtomEnum::WINDOW, &[window]).unwrap();
+			connection.flush().unwrap();
+			let (sender, receiver) = mpsc::channel();
+			remote.execute_on_current_profile(move |profile| { let _ = sender.send(profile.name().to_string()); });
+			if receiver.recv_timeout(Duration::from_millis(100)).is_ok_and(|profile_name| profile_name == "class_profile") {
+				return;
+			}
+			thread::sleep(Duration::from_millis(50));
+		}
+		panic!("X11 source did not activate the profile of the test window.");
+	}
+}
\ No newline at end of file